@@ -0,0 +1,242 @@
+//! actix-web integration, enabled with the `actix` feature.
+
+use std::{borrow::Cow, path::PathBuf, rc::Rc};
+
+use actix_web::{
+    dev::{AppService, HttpServiceFactory},
+    guard,
+    http::header,
+    web, HttpRequest, HttpResponse, HttpResponseBuilder,
+};
+use bytes::Bytes;
+use mime::Mime;
+
+use crate::{
+    embed::{etag_matches, EmbeddedAsset, EmbeddedAssets},
+    store::{AssetStore, ResolvedAsset},
+};
+
+/// Serves the assets with actix-web, from disk or embedded in the binary.
+///
+/// Files under the assets prefix, `/assets` by default, are served from the
+/// assets directory, and everything else from the public directory. Because
+/// this matches every path, it should be registered after all of your other routes.
+///
+/// Embedded assets are served with their ETag and `Cache-Control`, like
+/// [`CremeReleaseService`](crate::services::CremeReleaseService), but without
+/// ranges or precompressed variants. Files on disk are read on every request,
+/// which is meant for development.
+///
+/// Usually created with the [`actix_service!`](crate::actix_service) macro.
+#[derive(Debug, Clone)]
+pub struct CremeResource {
+    store: AssetStore,
+    /// The page served with a 404 status when nothing matches the request.
+    not_found_page: Option<String>,
+}
+
+impl CremeResource {
+    /// Serves the assets from disk.
+    pub fn new(assets_dir: PathBuf, public_dir: PathBuf) -> Self {
+        Self {
+            store: AssetStore::from_dirs(assets_dir, public_dir),
+            not_found_page: None,
+        }
+    }
+
+    /// Serves assets embedded by `creme_bundler`.
+    pub fn embedded(assets: EmbeddedAssets) -> Self {
+        Self {
+            store: AssetStore::embedded(assets),
+            not_found_page: None,
+        }
+    }
+
+    /// Serves assets under this URL prefix instead of `/assets`.
+    /// See [`AssetStore::assets_prefix`].
+    pub fn assets_prefix(self, prefix: impl Into<Cow<'static, str>>) -> Self {
+        Self {
+            store: self.store.assets_prefix(prefix),
+            ..self
+        }
+    }
+
+    /// Serves files with the given extension with this mime type, when they're
+    /// served from disk. Embedded assets already have the bundler's overrides applied.
+    pub fn mime_override(self, ext: impl AsRef<str>, mime: Mime) -> Self {
+        Self {
+            store: self.store.mime_override(ext, mime),
+            ..self
+        }
+    }
+
+    /// Serves a page from the public directory, like `404.html`, with a 404
+    /// status when nothing matches the request. Unknown assets still get an
+    /// empty 404.
+    pub fn not_found_page(self, path: impl Into<String>) -> Self {
+        Self {
+            not_found_page: Some(path.into()),
+            ..self
+        }
+    }
+
+    fn respond(&self, req: &HttpRequest) -> HttpResponse {
+        let path = req.path();
+
+        if let Some(asset) = self.store.find_embedded(path) {
+            return embedded_response(asset, req);
+        }
+
+        match self.store.get(path) {
+            Ok(Some(asset)) => asset_response(HttpResponse::Ok(), asset),
+            Ok(None) => self.not_found(path),
+            Err(_) => HttpResponse::InternalServerError().finish(),
+        }
+    }
+
+    fn not_found(&self, path: &str) -> HttpResponse {
+        // Unknown assets aren't pages, so they don't get the not found page.
+        let page = self
+            .not_found_page
+            .as_deref()
+            .filter(|_| self.store.strip_assets_prefix(path).is_none())
+            .and_then(|page| self.store.get(page).ok().flatten());
+
+        match page {
+            Some(page) => asset_response(HttpResponse::NotFound(), page),
+            None => HttpResponse::NotFound().finish(),
+        }
+    }
+}
+
+impl HttpServiceFactory for CremeResource {
+    fn register(self, config: &mut AppService) {
+        let resource = Rc::new(self);
+
+        web::resource("/{path:.*}")
+            .route(
+                web::route()
+                    .guard(guard::Any(guard::Get()).or(guard::Head()))
+                    .to(move |req: HttpRequest| {
+                        let resource = resource.clone();
+                        async move { resource.respond(&req) }
+                    }),
+            )
+            .register(config);
+    }
+}
+
+fn asset_response(mut builder: HttpResponseBuilder, asset: ResolvedAsset) -> HttpResponse {
+    let content = match asset.content {
+        Cow::Borrowed(content) => Bytes::from_static(content),
+        Cow::Owned(content) => Bytes::from(content),
+    };
+
+    builder.content_type(asset.mime.as_ref()).body(content)
+}
+
+fn embedded_response(asset: &'static EmbeddedAsset, req: &HttpRequest) -> HttpResponse {
+    let if_none_match = req.headers().get_all(header::IF_NONE_MATCH);
+    let not_modified = etag_matches(
+        if_none_match.filter_map(|value| value.to_str().ok()),
+        asset.etag,
+    );
+
+    let mut builder = if not_modified {
+        HttpResponse::NotModified()
+    } else {
+        HttpResponse::Ok()
+    };
+    builder
+        .insert_header((header::ETAG, asset.etag))
+        .insert_header((header::CACHE_CONTROL, asset.cache_control_header()));
+
+    if not_modified {
+        return builder.finish();
+    }
+
+    if let Some(content_disposition) = asset.content_disposition {
+        builder.insert_header((header::CONTENT_DISPOSITION, content_disposition));
+    }
+
+    builder
+        .content_type(asset.mime)
+        .body(Bytes::from_static(asset.content))
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::{http::StatusCode, test, App};
+
+    use super::*;
+
+    static ASSETS: &[EmbeddedAsset] = &[
+        asset("static/v1/app.js", "text/javascript", b"app"),
+        asset("404.html", "text/html", b"not found"),
+    ];
+
+    const fn asset(
+        path: &'static str,
+        mime: &'static str,
+        content: &'static [u8],
+    ) -> EmbeddedAsset {
+        EmbeddedAsset {
+            path,
+            mime,
+            etag: "\"data\"",
+            immutable: true,
+            cache_control: None,
+            content_disposition: None,
+            content,
+            encodings: &[],
+        }
+    }
+
+    #[test]
+    fn embedded_assets_are_served() {
+        actix_rt::System::new().block_on(async {
+            let resource = CremeResource::embedded(EmbeddedAssets::new(ASSETS))
+                .assets_prefix("/static/v1")
+                .not_found_page("404.html");
+            let app = test::init_service(App::new().service(resource)).await;
+
+            let request = test::TestRequest::get()
+                .uri("/static/v1/app.js")
+                .to_request();
+            let response = test::call_service(&app, request).await;
+            assert_eq!(response.status(), StatusCode::OK);
+            let headers = response.headers();
+            assert_eq!(
+                headers.get(header::CONTENT_TYPE).unwrap(),
+                "text/javascript"
+            );
+            assert_eq!(
+                headers.get(header::CACHE_CONTROL).unwrap(),
+                "public, max-age=31536000, immutable"
+            );
+            assert_eq!(test::read_body(response).await, "app");
+
+            let request = test::TestRequest::get()
+                .uri("/static/v1/app.js")
+                .insert_header((header::IF_NONE_MATCH, "\"data\""))
+                .to_request();
+            let response = test::call_service(&app, request).await;
+            assert_eq!(response.status(), StatusCode::NOT_MODIFIED);
+
+            // Unknown assets get an empty 404, and unknown pages the not found page.
+            let request = test::TestRequest::get()
+                .uri("/static/v1/missing.js")
+                .to_request();
+            let response = test::call_service(&app, request).await;
+            assert_eq!(response.status(), StatusCode::NOT_FOUND);
+            assert!(test::read_body(response).await.is_empty());
+
+            let request = test::TestRequest::get()
+                .uri("/assets/missing.js")
+                .to_request();
+            let response = test::call_service(&app, request).await;
+            assert_eq!(response.status(), StatusCode::NOT_FOUND);
+            assert_eq!(test::read_body(response).await, "not found");
+        });
+    }
+}
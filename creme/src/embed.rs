@@ -1,23 +1,152 @@
-use mime::Mime;
+use std::fmt;
 
-#[derive(Debug)]
+/// Cache-Control for content-hashed assets, which never change.
+const IMMUTABLE_CACHE_CONTROL: &str = "public, max-age=31536000, immutable";
+
+/// Cache-Control for everything else. Browsers revalidate with the ETag.
+const REVALIDATE_CACHE_CONTROL: &str = "no-cache";
+
+// Exported at the crate root by `#[macro_export]`, and re-exported below so
+// it's reachable as `creme::embed::include_manifest!()`.
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __include_manifest {
+    () => {{
+        static ASSETS: $crate::embed::EmbeddedAssets = ::core::include!(::core::env!(
+            "CREME_EMBED",
+            "no embedded assets, creme::embed::include_manifest!() needs creme_bundler to run in release mode"
+        ));
+        &ASSETS
+    }};
+}
+
+/// Includes the assets `creme_bundler` embedded in release mode, as a
+/// `&'static EmbeddedAssets`, without setting up a service. This is for
+/// serving or reading them outside of a web framework, like
+/// `creme::embed::include_manifest!().find("css/style.css")`.
+///
+/// Assets are only embedded in release mode, so this fails to compile
+/// when they were bundled in development mode.
+#[doc(inline)]
+pub use crate::__include_manifest as include_manifest;
+
+#[derive(Debug, Clone, Copy)]
 pub struct EmbeddedAssets {
     pub assets: &'static [EmbeddedAsset],
 }
 
 impl EmbeddedAssets {
-    pub fn new(assets: &'static [EmbeddedAsset]) -> Self {
+    pub const fn new(assets: &'static [EmbeddedAsset]) -> Self {
         Self { assets }
     }
 
     pub fn get(&self, index: usize) -> Option<&EmbeddedAsset> {
         self.assets.get(index)
     }
+
+    /// Finds an asset by its URL path. A leading `/` is ignored.
+    pub fn find(&self, path: &str) -> Option<&'static EmbeddedAsset> {
+        let path = path.trim_start_matches('/');
+        self.assets.iter().find(|asset| asset.path == path)
+    }
+
+    /// Rehashes every asset and checks it against the hash in its ETag, to catch
+    /// assets corrupted or patched after the binary was built.
+    ///
+    /// This reads all of the embedded content, so call it once at startup, like
+    /// `assets.verify().expect("embedded assets are corrupt")`.
+    pub fn verify(&self) -> Result<(), VerifyError> {
+        for asset in self.assets {
+            let actual = format!("\"{}\"", blake3::hash(asset.content).to_hex());
+
+            if actual != asset.etag {
+                return Err(VerifyError {
+                    path: asset.path,
+                    expected: asset.etag,
+                    actual,
+                });
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// An embedded asset whose content doesn't match its hash, from [`EmbeddedAssets::verify`].
+#[derive(Debug, Clone)]
+pub struct VerifyError {
+    /// The URL path of the asset.
+    pub path: &'static str,
+    /// The ETag the bundler generated.
+    pub expected: &'static str,
+    /// The ETag of the content in the binary.
+    pub actual: String,
+}
+
+impl fmt::Display for VerifyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "embedded asset {} doesn't match its hash: expected {}, found {}",
+            self.path, self.expected, self.actual
+        )
+    }
 }
 
+impl std::error::Error for VerifyError {}
+
+/// A file embedded into the binary.
+/// These are generated by `creme_bundler`, so every field is `'static`.
 #[derive(Debug)]
 pub struct EmbeddedAsset {
     pub path: &'static str,
-    pub mime: Mime,
+    pub mime: &'static str,
+    /// A strong ETag derived from the content hash, including the quotes.
+    pub etag: &'static str,
+    /// Whether the file name is content-hashed, so it can be cached forever.
+    pub immutable: bool,
+    /// A `Cache-Control` value set by the bundler's rules, used instead of the
+    /// default for hashed or unhashed files.
+    pub cache_control: Option<&'static str>,
+    /// A `Content-Disposition` value set by the bundler's download globs,
+    /// like `attachment; filename="guide.pdf"`.
+    pub content_disposition: Option<&'static str>,
+    pub content: &'static [u8],
+    /// Precompressed versions of `content`, in the order the server prefers them.
+    pub encodings: &'static [EncodedVariant],
+}
+
+impl EmbeddedAsset {
+    /// The `Cache-Control` this asset is served with: the bundler's, if it set
+    /// one, or forever for content-hashed files, and revalidating otherwise.
+    pub(crate) fn cache_control_header(&self) -> &'static str {
+        match self.cache_control {
+            Some(cache_control) => cache_control,
+            None if self.immutable => IMMUTABLE_CACHE_CONTROL,
+            None => REVALIDATE_CACHE_CONTROL,
+        }
+    }
+}
+
+/// Checks `If-None-Match` header values against an ETag, using the weak
+/// comparison the header calls for.
+pub(crate) fn etag_matches<'a>(
+    if_none_match: impl IntoIterator<Item = &'a str>,
+    etag: &str,
+) -> bool {
+    if_none_match
+        .into_iter()
+        .flat_map(|value| value.split(','))
+        .map(str::trim)
+        .any(|candidate| candidate == "*" || candidate.trim_start_matches("W/") == etag)
+}
+
+/// A precompressed version of an [`EmbeddedAsset`].
+#[derive(Debug)]
+pub struct EncodedVariant {
+    /// The `Content-Encoding` of `content`, like `br` or `gzip`.
+    pub encoding: &'static str,
+    /// The ETag of this representation, including the quotes.
+    pub etag: &'static str,
     pub content: &'static [u8],
 }
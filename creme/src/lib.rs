@@ -1,21 +1,105 @@
 pub use creme_macros::asset;
+pub use creme_macros::asset_bytes;
+pub use creme_macros::asset_concat;
+pub use creme_macros::data_uri;
+pub use creme_macros::manifest;
+pub use creme_macros::manifest_entries;
+pub use creme_macros::manifest_group;
+pub use creme_macros::preload;
+pub use creme_macros::runtime_service;
 pub use creme_macros::service;
+pub use creme_macros::srcset;
 
 pub use mime;
 
 pub mod services;
 pub mod embed;
+pub mod manifest;
+pub mod preload;
+mod store;
 
+pub use preload::preload_links;
+pub use store::{AssetStore, ResolvedAsset};
+
+#[cfg(feature = "actix")]
+pub mod actix;
+#[cfg(feature = "actix")]
+pub use creme_macros::actix_service;
+
+/// Whether the assets were bundled in release mode.
+///
+/// This can be used in `const` items. If `CREME_RELEASE_MODE` isn't set,
+/// like in a crate that doesn't run `creme_bundler`, this is `false`.
 #[macro_export]
 macro_rules! is_release {
     () => {
-        env!("CREME_RELEASE_MODE") == "release"
+        $crate::is_release_mode(::core::option_env!("CREME_RELEASE_MODE"))
     };
 }
 
+/// Whether the assets are served in development mode. The opposite of [`is_release!`].
+///
+/// This can be used in `const` items. If `CREME_RELEASE_MODE` isn't set,
+/// like in a crate that doesn't run `creme_bundler`, this is `true`.
 #[macro_export]
 macro_rules! is_development {
     () => {
-        env!("CREME_RELEASE_MODE") == "development"
+        !$crate::is_release!()
+    };
+}
+
+/// Checks a `CREME_RELEASE_MODE` value, in a way that works in `const` contexts.
+#[doc(hidden)]
+pub const fn is_release_mode(mode: Option<&str>) -> bool {
+    let Some(mode) = mode else {
+        return false;
+    };
+
+    let (mode, expected) = (mode.as_bytes(), b"release");
+    if mode.len() != expected.len() {
+        return false;
+    }
+
+    let mut i = 0;
+    while i < mode.len() {
+        if mode[i] != expected[i] {
+            return false;
+        }
+        i += 1;
+    }
+
+    true
+}
+
+/// Wraps the embedded assets in `Some` when the `embed` feature is enabled.
+/// Otherwise, the `include!` of the assets is never expanded, so they aren't
+/// compiled into the binary. Used by `runtime_service!()`.
+#[cfg(feature = "embed")]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __embedded_assets {
+    ($($assets:tt)*) => {
+        ::core::option::Option::Some($($assets)*)
+    };
+}
+
+#[cfg(not(feature = "embed"))]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __embedded_assets {
+    ($($assets:tt)*) => {
+        ::core::option::Option::None
+    };
+}
+
+/// Creates an [`AssetStore`] from the directories set up by `creme_bundler`.
+#[macro_export]
+macro_rules! asset_store {
+    () => {
+        $crate::AssetStore::from_dirs(
+            ::core::env!("CREME_ASSETS_DIR"),
+            ::core::env!("CREME_PUBLIC_DIR"),
+        )
+        .mime_overrides_from_env(::core::option_env!("CREME_MIME_OVERRIDES"))
     };
 }
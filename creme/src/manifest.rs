@@ -0,0 +1,117 @@
+//! Helpers for the `creme::manifest!()` and `creme::manifest_entries!()` macros,
+//! which read the asset manifest at runtime.
+
+use std::{
+    collections::{HashMap, HashSet},
+    fs,
+    path::{Path, PathBuf},
+};
+
+use serde::Deserialize;
+
+#[derive(Deserialize)]
+struct Manifest {
+    assets: HashMap<String, ManifestEntry>,
+}
+
+/// An asset in the manifest.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct ManifestEntry {
+    /// The URL the asset is served at, relative to the public directory,
+    /// like `assets/style-[hash].css`. Inlined assets have a `data:` URL.
+    pub url: String,
+    /// The mime type of the asset, like `text/css`.
+    pub mime: String,
+}
+
+/// Parses a manifest written by `creme_bundler` into a map of asset paths
+/// to their URLs, like `css/style.css` to `assets/style-[hash].css`.
+///
+/// # Panics
+///
+/// This panics if the manifest is invalid, which means it wasn't written
+/// by a matching version of `creme_bundler`.
+pub fn from_json(json: &str) -> HashMap<String, String> {
+    urls(entries_from_json(json))
+}
+
+/// Like [`from_json`], but maps each asset path to its whole entry.
+///
+/// # Panics
+///
+/// This panics if the manifest is invalid, which means it wasn't written
+/// by a matching version of `creme_bundler`.
+pub fn entries_from_json(json: &str) -> HashMap<String, ManifestEntry> {
+    let manifest: Manifest = serde_json::from_str(json).expect("Failed to parse manifest file");
+    manifest.assets
+}
+
+/// Lists the assets in a directory, mapping each to the URL it is served at
+/// in development, under the directory's name, like `css/style.css` to
+/// `assets/css/style.css`. Files that can't be read are left out.
+pub fn from_assets_dir(assets_dir: impl AsRef<Path>) -> HashMap<String, String> {
+    urls(entries_from_assets_dir(assets_dir))
+}
+
+/// Like [`from_assets_dir`], but maps each asset path to its whole entry.
+/// Without a manifest, mime types are guessed from the file extensions.
+pub fn entries_from_assets_dir(assets_dir: impl AsRef<Path>) -> HashMap<String, ManifestEntry> {
+    let assets_dir = assets_dir.as_ref();
+    let url = assets_dir
+        .file_name()
+        .map_or("assets".into(), |name| name.to_string_lossy());
+
+    let mut assets = HashMap::new();
+    add_dir(
+        assets_dir,
+        assets_dir,
+        &url,
+        &mut HashSet::new(),
+        &mut assets,
+    );
+    assets
+}
+
+fn urls(entries: HashMap<String, ManifestEntry>) -> HashMap<String, String> {
+    entries
+        .into_iter()
+        .map(|(path, entry)| (path, entry.url))
+        .collect()
+}
+
+fn add_dir(
+    root: &Path,
+    dir: &Path,
+    url: &str,
+    visited: &mut HashSet<PathBuf>,
+    assets: &mut HashMap<String, ManifestEntry>,
+) {
+    // Symlinks are followed, so skip directories that were already listed.
+    let Ok(canonical) = fs::canonicalize(dir) else {
+        return;
+    };
+    if !visited.insert(canonical) {
+        return;
+    }
+
+    let Ok(entries) = fs::read_dir(dir) else {
+        return;
+    };
+
+    for entry in entries.flatten() {
+        let path = entry.path();
+
+        if path.is_dir() {
+            add_dir(root, &path, url, visited, assets);
+        } else if let Ok(relative) = path.strip_prefix(root) {
+            let relative = relative.to_string_lossy().replace('\\', "/");
+            let entry = ManifestEntry {
+                url: format!("{url}/{relative}"),
+                mime: mime_guess::from_path(&relative)
+                    .first_or_octet_stream()
+                    .to_string(),
+            };
+            assets.insert(relative, entry);
+        }
+    }
+}
@@ -0,0 +1,118 @@
+use std::{collections::HashMap, fmt::Write as _};
+
+use mime::Mime;
+
+use crate::manifest::ManifestEntry;
+
+/// Picks the `as` value of a preload link from an asset's mime type, and
+/// whether the request needs `crossorigin`. Fonts are always fetched in
+/// CORS mode, so their preloads are ignored without it.
+///
+/// The `preload!` macro uses this too, so both pick the same value.
+pub fn destination(mime: &Mime) -> (&'static str, bool) {
+    match (mime.type_(), mime.subtype().as_str()) {
+        (mime::TEXT, "css") => ("style", false),
+        (mime::TEXT | mime::APPLICATION, "javascript") => ("script", false),
+        (mime::FONT, _) => ("font", true),
+        (mime::IMAGE, _) => ("image", false),
+        (mime::AUDIO, _) => ("audio", false),
+        (mime::VIDEO, _) => ("video", false),
+        _ => ("fetch", true),
+    }
+}
+
+/// Like [`destination`], for a mime type from the manifest. Anything that
+/// can't be parsed is fetched like an unknown type.
+fn destination_of(mime: &str) -> (&'static str, bool) {
+    let mime = mime.parse().unwrap_or(mime::APPLICATION_OCTET_STREAM);
+    destination(&mime)
+}
+
+/// Builds a `Link` header value that preloads `url`, for the `preload!` macro.
+#[doc(hidden)]
+pub fn link_header(url: &str, mime: &str) -> String {
+    let (destination, crossorigin) = destination_of(mime);
+
+    let mut link = format!("</{url}>; rel=preload; as={destination}");
+    if crossorigin {
+        link.push_str("; crossorigin");
+    }
+    link
+}
+
+/// Builds `<link rel="preload">` tags for the given assets, to put in a page's `<head>`.
+/// `manifest` is usually [`manifest_entries!()`](crate::manifest_entries!), and
+/// assets that aren't in it are left out.
+///
+/// The manifest is passed in rather than read here, since it's only known to the
+/// crate whose build script ran `creme_bundler`. `creme` itself is compiled
+/// without it, so a `preload_links(&[&str])` couldn't find it.
+///
+/// # Example
+/// ```rust,ignore
+/// let links = creme::preload_links(creme::manifest_entries!(), &["css/style.css"]);
+/// // <link rel="preload" href="/assets/style-[hash].css" as="style">
+/// ```
+pub fn preload_links(manifest: &HashMap<String, ManifestEntry>, paths: &[&str]) -> String {
+    let mut links = String::new();
+
+    // Inlined assets have nothing to preload.
+    let entries = paths
+        .iter()
+        .filter_map(|path| manifest.get(*path))
+        .filter(|entry| !entry.url.starts_with("data:"));
+
+    for ManifestEntry { url, mime } in entries {
+        let (destination, crossorigin) = destination_of(mime);
+
+        write!(links, r#"<link rel="preload" href="/{url}" as="{destination}""#).unwrap();
+        if crossorigin {
+            links.push_str(" crossorigin");
+        }
+        links.push('>');
+    }
+
+    links
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(path: &str, url: &str, mime: &str) -> (String, ManifestEntry) {
+        let entry = ManifestEntry {
+            url: url.to_string(),
+            mime: mime.to_string(),
+        };
+        (path.to_string(), entry)
+    }
+
+    #[test]
+    fn destination_comes_from_the_manifest() {
+        let manifest = HashMap::from([
+            entry("css/style.css", "assets/style-1234.css", "text/css"),
+            // Without an extension, the URL alone couldn't tell it's a font.
+            entry("fonts/inter", "assets/inter-1234", "font/woff2"),
+            entry("img/dot.png", "data:image/png;base64,", "image/png"),
+        ]);
+
+        let links = preload_links(&manifest, &["css/style.css", "fonts/inter", "img/dot.png"]);
+        assert_eq!(
+            links,
+            r#"<link rel="preload" href="/assets/style-1234.css" as="style">"#.to_string()
+                + r#"<link rel="preload" href="/assets/inter-1234" as="font" crossorigin>"#
+        );
+    }
+
+    #[test]
+    fn link_header() {
+        assert_eq!(
+            super::link_header("assets/app-1234.js", "text/javascript; charset=utf-8"),
+            "</assets/app-1234.js>; rel=preload; as=script"
+        );
+        assert_eq!(
+            super::link_header("assets/data.bin", "not a mime type"),
+            "</assets/data.bin>; rel=preload; as=fetch; crossorigin"
+        );
+    }
+}
@@ -1,33 +1,111 @@
 use std::{
+    borrow::Cow,
     convert::Infallible,
-    path::PathBuf,
+    path::{Path, PathBuf},
     pin::Pin,
-    task::{Context, Poll},
+    task::{ready, Context, Poll},
 };
 
 use bytes::Bytes;
-use futures_util::{Future, FutureExt};
-use http::{Request, Response, StatusCode};
-use http_body::{combinators::UnsyncBoxBody, Body, Empty};
-use tower::Service;
-use tower_http::services::fs::{
-    DefaultServeDirFallback, ServeDir, ServeFileSystemResponseBody as ResponseBody,
+use futures_util::{future, Future, FutureExt};
+use http::{header, HeaderValue, Request, Response, StatusCode};
+use http_body::{combinators::UnsyncBoxBody, Body, Empty, Full};
+use mime::Mime;
+use tower::{Service, ServiceExt};
+use super::LiveReload;
+use crate::store::AssetStore;
+
+use tower_http::services::{
+    fs::{DefaultServeDirFallback, ServeDir, ServeFileSystemResponseBody as ResponseBody},
+    ServeFile,
 };
 
-#[derive(Clone)]
 pub struct CremeDevService<F = DefaultServeDirFallback> {
     asset_service: ServeDir<F>,
+    overlay_service: Option<ServeDir>,
     public_service: ServeDir<F>,
+    public_dir: PathBuf,
+    /// Routes requests to the assets or public directory, and holds the mime overrides.
+    store: AssetStore,
+    /// The page served when nothing matches, with the status it's served with.
+    fallback_page: Option<(ServeFile, StatusCode)>,
+    /// The body of responses to files that couldn't be read, instead of an empty one.
+    error_body: Option<Bytes>,
+    /// Whether the inner services returned ready since the last call, so they
+    /// aren't polled again before it.
+    asset_ready: bool,
+    public_ready: bool,
+}
+
+// Tower clones services after polling them, so clones have to be polled
+// again before they're called.
+impl<F: Clone> Clone for CremeDevService<F> {
+    fn clone(&self) -> Self {
+        Self {
+            asset_service: self.asset_service.clone(),
+            overlay_service: self.overlay_service.clone(),
+            public_service: self.public_service.clone(),
+            public_dir: self.public_dir.clone(),
+            store: self.store.clone(),
+            fallback_page: self.fallback_page.clone(),
+            error_body: self.error_body.clone(),
+            asset_ready: false,
+            public_ready: false,
+        }
+    }
 }
 
 impl CremeDevService {
     pub fn new(assets_dir: PathBuf, public_dir: PathBuf) -> Self {
         Self {
+            store: AssetStore::from_dirs(&assets_dir, &public_dir),
             asset_service: ServeDir::new(assets_dir),
-            public_service: ServeDir::new(public_dir),
+            overlay_service: None,
+            public_service: ServeDir::new(&public_dir),
+            public_dir,
+            fallback_page: None,
+            error_body: None,
+            asset_ready: false,
+            public_ready: false,
         }
     }
 
+    /// Serves a page from the public directory, like `404.html`, with a 404
+    /// status when nothing matches the request. Unknown assets still get an
+    /// empty 404. `creme::service!()` sets this up from the bundler's `not_found_page`.
+    pub fn not_found_page(self, path: impl AsRef<Path>) -> Self {
+        let page = ServeFile::new(self.public_dir.join(path));
+
+        Self {
+            fallback_page: Some((page, StatusCode::NOT_FOUND)),
+            ..self
+        }
+    }
+
+    /// Serves a file from the public directory, like a single-page app's
+    /// `index.html`, with a 200 status when nothing matches the request.
+    /// Unknown assets still get an empty 404. It replaces the not found page.
+    pub fn fallback_file(self, path: impl AsRef<Path>) -> Self {
+        let page = ServeFile::new(self.public_dir.join(path));
+
+        Self {
+            fallback_page: Some((page, StatusCode::OK)),
+            ..self
+        }
+    }
+
+    /// Serves assets from `overlay_dir` before the assets directory, like the
+    /// bundler's environment overlays, such as `assets.staging`.
+    /// `creme::service!()` sets this up when the bundler found an overlay.
+    pub fn overlay_dir(self, overlay_dir: PathBuf) -> Self {
+        Self {
+            overlay_service: Some(ServeDir::new(overlay_dir)),
+            ..self
+        }
+    }
+
+    /// Sets a service to call when no file matches the request.
+    /// It replaces the not found page or fallback file, if one is set.
     // TODO: This is a bit of a hack, requiring a clone.
     // We can downcast the fallback service to get around this eventually.
     pub fn fallback<F2>(self, new_fallback: F2) -> CremeDevService<F2>
@@ -36,7 +114,73 @@ impl CremeDevService {
     {
         CremeDevService {
             asset_service: self.asset_service.fallback(new_fallback.clone()),
+            overlay_service: self.overlay_service,
             public_service: self.public_service.fallback(new_fallback),
+            public_dir: self.public_dir,
+            store: self.store,
+            fallback_page: None,
+            error_body: self.error_body,
+            asset_ready: false,
+            public_ready: false,
+        }
+    }
+
+    /// Injects a live-reload script into HTML files served by this service.
+    /// To also reload pages rendered by your own routes, wrap the whole app
+    /// in a [`LiveReloadLayer`](super::LiveReloadLayer) instead.
+    pub fn with_livereload(self) -> LiveReload<Self> {
+        LiveReload::new(self)
+    }
+}
+
+impl<F> CremeDevService<F> {
+    /// Serves files with the given extension with this mime type,
+    /// instead of the one guessed from the extension.
+    /// `creme::service!()` sets these up from the bundler's overrides.
+    pub fn mime_override(self, ext: impl AsRef<str>, mime: Mime) -> Self {
+        Self {
+            store: self.store.mime_override(ext, mime),
+            ..self
+        }
+    }
+
+    /// Serves assets under this URL prefix instead of `/assets`, like `/static`
+    /// for `/static/img/cat.jpeg`. The slashes around it are optional.
+    ///
+    /// The bundler's development manifest points at the assets directory's
+    /// name, and `creme::service!()` sets this up to match it.
+    ///
+    /// # Panics
+    ///
+    /// This will panic if the prefix is empty, like `/`, since assets would
+    /// shadow every public file.
+    pub fn assets_prefix(self, prefix: impl Into<Cow<'static, str>>) -> Self {
+        Self {
+            store: self.store.assets_prefix(prefix),
+            ..self
+        }
+    }
+
+    /// Serves `index.html` for directory paths in the public directory, like
+    /// `public/docs/index.html` for `/docs/`. Enabled by default.
+    ///
+    /// Either way, directory paths without a trailing slash, like `/docs`,
+    /// are redirected to one.
+    pub fn append_index_html(self, append_index_html: bool) -> Self {
+        Self {
+            public_service: self
+                .public_service
+                .append_index_html_on_directories(append_index_html),
+            ..self
+        }
+    }
+
+    /// Sets the body of the 404 and 500 responses sent when a file can't be read,
+    /// like when it's deleted while being served, instead of an empty one.
+    pub fn with_error_body(self, body: impl Into<Bytes>) -> Self {
+        Self {
+            error_body: Some(body.into()),
+            ..self
         }
     }
 }
@@ -59,42 +203,124 @@ where
 
     #[inline]
     fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
-        let asset_ready = self.asset_service.poll_ready(cx);
-        let public_ready = self.public_service.poll_ready(cx);
-
-        if asset_ready.is_ready() && public_ready.is_ready() {
-            Poll::Ready(Ok(()))
-        } else {
-            Poll::Pending
+        if !self.asset_ready {
+            ready!(self.asset_service.poll_ready(cx))?;
+            self.asset_ready = true;
+        }
+        if !self.public_ready {
+            ready!(self.public_service.poll_ready(cx))?;
+            self.public_ready = true;
         }
+
+        Poll::Ready(Ok(()))
     }
 
     fn call(&mut self, req: Request<ReqBody>) -> Self::Future {
-        if req.uri().path().starts_with("/assets") {
-            let req = Request::builder()
-                .uri(
-                    req.uri()
-                        .path_and_query()
-                        .unwrap()
-                        .as_str()
-                        .strip_prefix("/assets")
-                        .unwrap(),
-                )
-                .body(req.into_body())
-                .unwrap();
-
-            self.asset_service.try_call(req)
+        let mime_override = self
+            .store
+            .mime_override_for(req.uri().path())
+            .map(|mime| HeaderValue::from_str(mime.as_ref()).unwrap());
+
+        let asset_uri = req
+            .uri()
+            .path_and_query()
+            .and_then(|path| self.store.strip_assets_prefix(path.as_str()))
+            .map(Cow::into_owned);
+
+        // Unknown assets aren't pages, so they don't get the fallback page.
+        let fallback_page = self.fallback_page.clone().filter(|_| asset_uri.is_none());
+        let error_body = self.error_body.clone();
+
+        if let Some(asset_uri) = asset_uri {
+            let overlay_req = self.overlay_service.as_ref().map(|_| {
+                let mut overlay_req = Request::builder()
+                    .method(req.method().clone())
+                    .uri(&asset_uri)
+                    .body(Empty::<Bytes>::new())
+                    .unwrap();
+                *overlay_req.headers_mut() = req.headers().clone();
+                overlay_req
+            });
+
+            // Only the path changes, so conditional and range headers, like
+            // `If-Modified-Since`, still get 304s and partial responses.
+            let (mut parts, body) = req.into_parts();
+            parts.uri = asset_uri.parse().unwrap();
+            let req = Request::from_parts(parts, body);
+
+            match (self.overlay_service.clone(), overlay_req) {
+                // Assets missing from the overlay come from the assets directory.
+                (Some(mut overlay_service), Some(overlay_req)) => {
+                    let mut asset_service = self.asset_service.clone();
+
+                    // These are fresh clones, so they're polled before they're called.
+                    async move {
+                        ServiceExt::<Request<Empty<Bytes>>>::ready(&mut overlay_service)
+                            .await
+                            .unwrap_or_else(|err| match err {});
+
+                        match overlay_service.try_call(overlay_req).await {
+                            Ok(response) if response.status() == StatusCode::NOT_FOUND => {
+                                ServiceExt::<Request<ReqBody>>::ready(&mut asset_service)
+                                    .await
+                                    .unwrap_or_else(|err| match err {});
+                                asset_service.try_call(req).await
+                            }
+                            result => result,
+                        }
+                    }
+                    .left_future()
+                }
+                _ => {
+                    self.asset_ready = false;
+                    self.asset_service.try_call(req).right_future()
+                }
+            }
+            .left_future()
         } else {
-            self.public_service.try_call(req)
+            self.public_ready = false;
+            self.public_service.try_call(req).right_future()
         }
+        .then(|result| match (result, fallback_page) {
+            (Ok(response), Some((page, status))) if response.status() == StatusCode::NOT_FOUND => {
+                page.oneshot(Request::new(Empty::<Bytes>::new()))
+                    .map(move |result| match result {
+                        Ok(mut response) => {
+                            *response.status_mut() = status;
+                            Ok(response)
+                        }
+                        Err(err) => match err {},
+                    })
+                    .left_future()
+            }
+            (result, _) => future::ready(result).right_future(),
+        })
         .map(
-            |result: Result<Response<ResponseBody>, std::io::Error>| -> Result<Self::Response, Infallible> {
+            move |result: Result<Response<ResponseBody>, std::io::Error>| -> Result<Self::Response, Infallible> {
                 let response = result
-                    .map(|response| Response::new(response.boxed_unsync()))
-                    .unwrap_or_else(|_err| {
-                        let body = Empty::new().map_err(|err| match err {}).boxed_unsync();
+                    .map(|response| {
+                        let mut response = response.map(|body| body.boxed_unsync());
+
+                        if let Some(mime) = mime_override.filter(|_| response.status().is_success()) {
+                            response.headers_mut().insert(header::CONTENT_TYPE, mime);
+                        }
+
+                        response
+                    })
+                    .unwrap_or_else(|err| {
+                        // ServeDir already answers missing files with a 404 response, but
+                        // a file can still vanish between lookup and open.
+                        let status = match err.kind() {
+                            std::io::ErrorKind::NotFound => StatusCode::NOT_FOUND,
+                            _ => StatusCode::INTERNAL_SERVER_ERROR,
+                        };
+
+                        let body = match error_body {
+                            Some(body) => Full::new(body).map_err(|err| match err {}).boxed_unsync(),
+                            None => Empty::new().map_err(|err| match err {}).boxed_unsync(),
+                        };
                         Response::builder()
-                            .status(StatusCode::INTERNAL_SERVER_ERROR)
+                            .status(status)
                             .body(body)
                             .unwrap()
                     });
@@ -103,3 +329,153 @@ where
         .boxed()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::*;
+
+    fn get(path: &str) -> Request<Empty<Bytes>> {
+        Request::get(path).body(Empty::new()).unwrap()
+    }
+
+    #[tokio::test]
+    async fn missing_asset_is_not_found() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir(dir.path().join("assets")).unwrap();
+        fs::write(dir.path().join("assets/style.css"), "body {}").unwrap();
+        let service = CremeDevService::new(dir.path().join("assets"), dir.path().into());
+
+        let found = service
+            .clone()
+            .oneshot(get("/assets/style.css"))
+            .await
+            .unwrap();
+        assert_eq!(found.status(), StatusCode::OK);
+
+        let missing = service.oneshot(get("/assets/missing.css")).await.unwrap();
+        assert_eq!(missing.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn custom_assets_prefix() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir(dir.path().join("static")).unwrap();
+        fs::write(dir.path().join("static/style.css"), "body {}").unwrap();
+        let service = CremeDevService::new(dir.path().join("static"), dir.path().join("public"))
+            .assets_prefix("static");
+
+        let response = service
+            .clone()
+            .oneshot(get("/static/style.css?v=1"))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        for path in ["/assets/style.css", "/staticx/style.css"] {
+            let response = service.clone().oneshot(get(path)).await.unwrap();
+            assert_eq!(response.status(), StatusCode::NOT_FOUND, "{path}");
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "the assets prefix can't be empty")]
+    fn empty_assets_prefix() {
+        let dir = tempfile::tempdir().unwrap();
+        CremeDevService::new(dir.path().into(), dir.path().into()).assets_prefix("/");
+    }
+
+    #[tokio::test]
+    async fn clones_are_polled_again() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut service = CremeDevService::new(dir.path().into(), dir.path().into());
+
+        ServiceExt::<Request<Empty<Bytes>>>::ready(&mut service)
+            .await
+            .unwrap();
+        assert!(service.asset_ready && service.public_ready);
+
+        let clone = service.clone();
+        assert!(!clone.asset_ready && !clone.public_ready);
+    }
+
+    #[tokio::test]
+    async fn overlay_falls_back_to_assets() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir(dir.path().join("assets")).unwrap();
+        fs::create_dir(dir.path().join("overlay")).unwrap();
+        fs::write(dir.path().join("assets/a.txt"), "assets").unwrap();
+        fs::write(dir.path().join("assets/b.txt"), "assets").unwrap();
+        fs::write(dir.path().join("overlay/a.txt"), "overlay").unwrap();
+        let service = CremeDevService::new(dir.path().join("assets"), dir.path().into())
+            .overlay_dir(dir.path().join("overlay"));
+
+        for (path, expected) in [("/assets/a.txt", "overlay"), ("/assets/b.txt", "assets")] {
+            let response = service.clone().oneshot(get(path)).await.unwrap();
+            assert_eq!(response.status(), StatusCode::OK);
+
+            let mut body = response.into_body();
+            let mut content = Vec::new();
+            while let Some(chunk) = body.data().await {
+                content.extend_from_slice(&chunk.unwrap());
+            }
+            assert_eq!(content, expected.as_bytes());
+        }
+    }
+
+    async fn get_body(service: &CremeDevService, path: &str) -> (StatusCode, Vec<u8>) {
+        let response = service.clone().oneshot(get(path)).await.unwrap();
+        let status = response.status();
+
+        let mut body = response.into_body();
+        let mut content = Vec::new();
+        while let Some(chunk) = body.data().await {
+            content.extend_from_slice(&chunk.unwrap());
+        }
+        (status, content)
+    }
+
+    #[tokio::test]
+    async fn directories_serve_their_index() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir_all(dir.path().join("public/docs")).unwrap();
+        fs::write(dir.path().join("public/index.html"), "home").unwrap();
+        fs::write(dir.path().join("public/docs/index.html"), "docs").unwrap();
+        let service = CremeDevService::new(dir.path().join("assets"), dir.path().join("public"));
+
+        let home = get_body(&service, "/").await;
+        assert_eq!(home, (StatusCode::OK, b"home".to_vec()));
+        let docs = get_body(&service, "/docs/").await;
+        assert_eq!(docs, (StatusCode::OK, b"docs".to_vec()));
+
+        let response = service.clone().oneshot(get("/docs?page=2")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::TEMPORARY_REDIRECT);
+        assert_eq!(response.headers()[header::LOCATION], "/docs/?page=2");
+
+        let service = service.append_index_html(false);
+        assert_eq!(get_body(&service, "/").await.0, StatusCode::NOT_FOUND);
+        assert_eq!(get_body(&service, "/docs/").await.0, StatusCode::NOT_FOUND);
+        let docs = get_body(&service, "/docs/index.html").await;
+        assert_eq!(docs, (StatusCode::OK, b"docs".to_vec()));
+    }
+
+    #[tokio::test]
+    async fn mime_overrides_are_served() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir(dir.path().join("assets")).unwrap();
+        fs::write(dir.path().join("assets/site.webmanifest"), "{}").unwrap();
+        let service = CremeDevService::new(dir.path().join("assets"), dir.path().into())
+            .mime_override("webmanifest", "application/manifest+json".parse().unwrap());
+
+        let response = service
+            .oneshot(get("/assets/site.webmanifest"))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(
+            response.headers()[header::CONTENT_TYPE],
+            "application/manifest+json"
+        );
+    }
+}
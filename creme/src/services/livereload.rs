@@ -0,0 +1,232 @@
+use std::{
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use bytes::{Bytes, BytesMut};
+use futures_util::{future, Future, FutureExt};
+use http::{header, HeaderValue, Method, Request, Response, StatusCode};
+use http_body::{combinators::UnsyncBoxBody, Body, Empty, Full};
+use tower::{Layer, Service};
+
+type BoxError = Box<dyn std::error::Error + Send + Sync>;
+
+/// The path prefix of the endpoints used by the injected script.
+const LIVERELOAD_PATH: &str = "/_creme/livereload";
+
+/// The injected script long-polls the server. When the connection drops (the
+/// server restarted), it waits for the server to come back and reloads the page.
+const LIVERELOAD_SCRIPT: &str = r#"<script>
+(async () => {
+    try { await fetch("/_creme/livereload/poll"); } catch (_) {}
+    for (;;) {
+        await new Promise((resolve) => setTimeout(resolve, 100));
+        try {
+            if ((await fetch("/_creme/livereload/ready")).ok) {
+                return location.reload();
+            }
+        } catch (_) {}
+    }
+})();
+</script>"#;
+
+/// A layer that injects a live-reload script into HTML responses,
+/// and serves the endpoints the script talks to.
+///
+/// This is meant for development only. The browser reloads whenever the
+/// server restarts, such as after `cargo watch` rebuilds it.
+#[derive(Clone, Default)]
+pub struct LiveReloadLayer;
+
+impl LiveReloadLayer {
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+impl<S> Layer<S> for LiveReloadLayer {
+    type Service = LiveReload<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        LiveReload { inner }
+    }
+}
+
+/// A service that injects a live-reload script into HTML responses.
+/// See [`LiveReloadLayer`].
+#[derive(Clone)]
+pub struct LiveReload<S> {
+    inner: S,
+}
+
+impl<S> LiveReload<S> {
+    pub fn new(inner: S) -> Self {
+        Self { inner }
+    }
+}
+
+impl<ReqBody, S, ResBody> Service<Request<ReqBody>> for LiveReload<S>
+where
+    S: Service<Request<ReqBody>, Response = Response<ResBody>>,
+    S::Error: Send + 'static,
+    S::Future: Send + 'static,
+    ResBody: Body<Data = Bytes> + Send + 'static,
+    ResBody::Error: Into<BoxError>,
+{
+    type Response = Response<UnsyncBoxBody<Bytes, BoxError>>;
+    type Error = S::Error;
+    type Future =
+        Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send + 'static>>;
+
+    #[inline]
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: Request<ReqBody>) -> Self::Future {
+        match req.uri().path().strip_prefix(LIVERELOAD_PATH) {
+            // Never answered, the connection only ends when the server goes down.
+            Some("/poll") => return future::pending().boxed(),
+            Some("/ready") => {
+                let response = Response::new(empty_body());
+                return future::ready(Ok(response)).boxed();
+            }
+            _ => {}
+        }
+
+        let is_head = req.method() == Method::HEAD;
+        let response = self.inner.call(req);
+        async move {
+            let response = response.await?;
+            Ok(if is_head {
+                response.map(|body| body.map_err(Into::into).boxed_unsync())
+            } else {
+                inject_script(response).await
+            })
+        }
+        .boxed()
+    }
+}
+
+fn empty_body() -> UnsyncBoxBody<Bytes, BoxError> {
+    Empty::new().map_err(|err| match err {}).boxed_unsync()
+}
+
+/// Whether the response is a whole HTML page the script can be added to.
+/// Partial and encoded bodies can't be edited, and other statuses, like a
+/// 304 or a redirect, have no page to add it to.
+fn is_injectable<B>(response: &Response<B>) -> bool {
+    let headers = response.headers();
+
+    response.status() == StatusCode::OK
+        && !headers.contains_key(header::CONTENT_RANGE)
+        && !headers.contains_key(header::CONTENT_ENCODING)
+        && headers
+            .get(header::CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .is_some_and(|value| value.starts_with("text/html"))
+}
+
+/// Buffers an HTML response and inserts the script before `</body>`,
+/// or at the end if there is none. Other responses are passed through.
+async fn inject_script<B>(response: Response<B>) -> Response<UnsyncBoxBody<Bytes, BoxError>>
+where
+    B: Body<Data = Bytes> + Send + 'static,
+    B::Error: Into<BoxError>,
+{
+    if !is_injectable(&response) {
+        return response.map(|body| body.map_err(Into::into).boxed_unsync());
+    }
+
+    let (mut parts, body) = response.into_parts();
+
+    let mut body = Box::pin(body);
+    let mut html = BytesMut::new();
+    while let Some(chunk) = body.data().await {
+        match chunk {
+            Ok(chunk) => html.extend_from_slice(&chunk),
+            Err(_) => {
+                let mut response = Response::new(empty_body());
+                *response.status_mut() = StatusCode::INTERNAL_SERVER_ERROR;
+                return response;
+            }
+        }
+    }
+
+    let insert_at = html
+        .windows(b"</body>".len())
+        .rposition(|window| window.eq_ignore_ascii_case(b"</body>"))
+        .unwrap_or(html.len());
+
+    let mut injected = BytesMut::with_capacity(html.len() + LIVERELOAD_SCRIPT.len());
+    injected.extend_from_slice(&html[..insert_at]);
+    injected.extend_from_slice(LIVERELOAD_SCRIPT.as_bytes());
+    injected.extend_from_slice(&html[insert_at..]);
+
+    parts
+        .headers
+        .insert(header::CONTENT_LENGTH, HeaderValue::from(injected.len()));
+
+    let body = Full::new(injected.freeze())
+        .map_err(|err| match err {})
+        .boxed_unsync();
+
+    Response::from_parts(parts, body)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::convert::Infallible;
+
+    use tower::{service_fn, ServiceExt};
+
+    use super::*;
+
+    fn html(
+        status: StatusCode,
+        headers: &[(header::HeaderName, &'static str)],
+    ) -> Response<Full<Bytes>> {
+        let mut response = Response::new(Full::from("<body></body>"));
+        *response.status_mut() = status;
+        response
+            .headers_mut()
+            .insert(header::CONTENT_TYPE, HeaderValue::from_static("text/html"));
+        for (name, value) in headers {
+            response
+                .headers_mut()
+                .insert(name, HeaderValue::from_static(value));
+        }
+        response
+    }
+
+    /// Whether the script was injected into `response`, served for a `method` request.
+    async fn injected(method: Method, response: Response<Full<Bytes>>) -> bool {
+        let mut response = Some(response);
+        let service = LiveReload::new(service_fn(move |_: Request<Empty<Bytes>>| {
+            future::ready(Ok::<_, Infallible>(response.take().unwrap()))
+        }));
+
+        let request = Request::builder()
+            .method(method)
+            .body(Empty::new())
+            .unwrap();
+        let mut body = service.oneshot(request).await.unwrap().into_body();
+        let mut content = Vec::new();
+        while let Some(chunk) = body.data().await {
+            content.extend_from_slice(&chunk.unwrap());
+        }
+        content != b"<body></body>"
+    }
+
+    #[tokio::test]
+    async fn only_whole_pages_get_the_script() {
+        assert!(injected(Method::GET, html(StatusCode::OK, &[])).await);
+
+        assert!(!injected(Method::HEAD, html(StatusCode::OK, &[])).await);
+        assert!(!injected(Method::GET, html(StatusCode::NOT_FOUND, &[])).await);
+        let partial = html(StatusCode::OK, &[(header::CONTENT_RANGE, "bytes 0-12/20")]);
+        assert!(!injected(Method::GET, partial).await);
+        let encoded = html(StatusCode::OK, &[(header::CONTENT_ENCODING, "gzip")]);
+        assert!(!injected(Method::GET, encoded).await);
+    }
+}
@@ -1,5 +1,9 @@
 mod dev_service;
+mod livereload;
 mod release_service;
+mod runtime_service;
 
 pub use dev_service::CremeDevService;
-// pub use release_service::CremeReleaseService;
+pub use livereload::{LiveReload, LiveReloadLayer};
+pub use release_service::CremeReleaseService;
+pub use runtime_service::CremeService;
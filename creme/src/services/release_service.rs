@@ -0,0 +1,683 @@
+use std::{
+    borrow::Cow,
+    convert::Infallible,
+    ops::Range,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use bytes::Bytes;
+use futures_util::{future, Future, FutureExt};
+use http::{header, HeaderMap, HeaderValue, Request, Response, StatusCode};
+use http_body::{combinators::UnsyncBoxBody, Body, Empty, SizeHint};
+use tower::{Service, ServiceExt};
+use tower_http::services::fs::DefaultServeDirFallback;
+
+use crate::embed::{etag_matches, EmbeddedAsset, EmbeddedAssets, EncodedVariant};
+use crate::store::AssetStore;
+
+/// The largest body frame an asset is served in, by default.
+const DEFAULT_CHUNK_SIZE: usize = 64 * 1024;
+
+/// Serves assets embedded into the binary by `creme_bundler`.
+#[derive(Clone)]
+pub struct CremeReleaseService<F = DefaultServeDirFallback> {
+    /// Looks up the embedded assets, and tells assets from pages.
+    store: AssetStore,
+    fallback: Option<F>,
+    /// The page served when nothing matches, with the status it's served with.
+    fallback_page: Option<(&'static EmbeddedAsset, StatusCode)>,
+    append_index_html: bool,
+    chunk_size: usize,
+}
+
+impl CremeReleaseService {
+    pub fn new(assets: EmbeddedAssets) -> Self {
+        Self {
+            store: AssetStore::embedded(assets),
+            fallback: None,
+            fallback_page: None,
+            append_index_html: true,
+            chunk_size: DEFAULT_CHUNK_SIZE,
+        }
+    }
+
+    /// Serves an embedded page, like `404.html`, with a 404 status when nothing
+    /// matches the request. Unknown assets still get an empty 404.
+    /// `creme::service!()` sets this up from the bundler's `not_found_page`.
+    ///
+    /// # Panics
+    ///
+    /// This will panic if the page isn't embedded.
+    pub fn not_found_page(self, path: &str) -> Self {
+        let page = self
+            .store
+            .find_embedded(path)
+            .unwrap_or_else(|| panic!("not found page {path} is not embedded"));
+
+        Self {
+            fallback_page: Some((page, StatusCode::NOT_FOUND)),
+            ..self
+        }
+    }
+
+    /// Serves an embedded file, like a single-page app's `index.html`, with a
+    /// 200 status when nothing matches the request. Unknown assets still get an
+    /// empty 404. It replaces the not found page.
+    ///
+    /// # Panics
+    ///
+    /// This will panic if the file isn't embedded.
+    pub fn fallback_file(self, path: &str) -> Self {
+        let page = self
+            .store
+            .find_embedded(path)
+            .unwrap_or_else(|| panic!("fallback file {path} is not embedded"));
+
+        Self {
+            fallback_page: Some((page, StatusCode::OK)),
+            ..self
+        }
+    }
+
+    /// Sets a service to call when no embedded asset matches the request.
+    /// It replaces the not found page or fallback file, if one is set.
+    pub fn fallback<F2>(self, new_fallback: F2) -> CremeReleaseService<F2> {
+        CremeReleaseService {
+            store: self.store,
+            fallback: Some(new_fallback),
+            fallback_page: None,
+            append_index_html: self.append_index_html,
+            chunk_size: self.chunk_size,
+        }
+    }
+}
+
+impl<F> CremeReleaseService<F> {
+    /// Serves the embedded `index.html` for directory paths, like `docs/index.html`
+    /// for `/docs/`, like [`ServeDir`] does. Enabled by default.
+    ///
+    /// Either way, directory paths without a trailing slash, like `/docs`,
+    /// are redirected to one.
+    ///
+    /// [`ServeDir`]: tower_http::services::ServeDir
+    pub fn append_index_html(self, append_index_html: bool) -> Self {
+        Self {
+            append_index_html,
+            ..self
+        }
+    }
+
+    /// Treats requests under this URL prefix instead of `/assets` as assets, like
+    /// `/static/v1`, so unknown ones get an empty 404 rather than the not found
+    /// page or fallback file. The slashes around it are optional.
+    ///
+    /// `creme::service!()` sets this up from where the assets directory is in
+    /// the public directory.
+    ///
+    /// # Panics
+    ///
+    /// This will panic if the prefix is empty, like `/`.
+    pub fn assets_prefix(self, prefix: impl Into<Cow<'static, str>>) -> Self {
+        Self {
+            store: self.store.assets_prefix(prefix),
+            ..self
+        }
+    }
+
+    /// Sets the largest body frame an asset is served in, 64 KiB by default.
+    /// Large assets, like videos, are served in several frames, so the server
+    /// can interleave them with other responses and slow clients push back.
+    /// The frames point into the binary, so nothing is copied either way.
+    ///
+    /// # Panics
+    ///
+    /// This will panic if `bytes` is 0.
+    pub fn chunk_size(self, bytes: usize) -> Self {
+        assert!(bytes > 0, "chunk size must be larger than 0");
+
+        Self {
+            chunk_size: bytes,
+            ..self
+        }
+    }
+}
+
+impl<ReqBody, F, FResBody> Service<Request<ReqBody>> for CremeReleaseService<F>
+where
+    F: Service<Request<ReqBody>, Response = Response<FResBody>, Error = Infallible>
+        + Clone
+        + Send
+        + 'static,
+    F::Future: Send + 'static,
+    ReqBody: Send + 'static,
+    FResBody: http_body::Body<Data = Bytes> + Send + 'static,
+    FResBody::Error: Into<Box<dyn std::error::Error + Send + Sync>>,
+{
+    type Response = Response<UnsyncBoxBody<Bytes, std::io::Error>>;
+    type Error = Infallible;
+    type Future =
+        Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send + 'static>>;
+
+    #[inline]
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, req: Request<ReqBody>) -> Self::Future {
+        if let Some(asset) = self.store.find_embedded(req.uri().path()) {
+            let response = asset_response(asset, req.headers(), self.chunk_size);
+            return future::ready(Ok(response)).boxed();
+        }
+
+        let path = req.uri().path();
+
+        if path.ends_with('/') {
+            let index = self
+                .store
+                .find_embedded(&format!("{path}index.html"))
+                .filter(|_| self.append_index_html);
+
+            if let Some(index) = index {
+                let response = asset_response(index, req.headers(), self.chunk_size);
+                return future::ready(Ok(response)).boxed();
+            }
+        } else if is_directory(&self.store.embedded_assets(), path) {
+            let location = match req.uri().query() {
+                Some(query) => format!("{path}/?{query}"),
+                None => format!("{path}/"),
+            };
+
+            let mut response = empty_response(StatusCode::TEMPORARY_REDIRECT);
+            response
+                .headers_mut()
+                .insert(header::LOCATION, HeaderValue::from_str(&location).unwrap());
+            return future::ready(Ok(response)).boxed();
+        }
+
+        match self.fallback.clone() {
+            Some(fallback) => fallback
+                .oneshot(req)
+                .map(|result| {
+                    result.map(|response| {
+                        response.map(|body| {
+                            body.map_err(std::io::Error::other)
+                                .boxed_unsync()
+                        })
+                    })
+                })
+                .boxed(),
+            None => {
+                // Unknown assets aren't pages, so they don't get the fallback page.
+                let is_asset = self.store.strip_assets_prefix(req.uri().path()).is_some();
+
+                let response = match self.fallback_page.filter(|_| !is_asset) {
+                    Some((page, StatusCode::NOT_FOUND)) => {
+                        not_found_page_response(page, req.headers(), self.chunk_size)
+                    }
+                    // The fallback file is the same page on every route, so its
+                    // ETag still applies.
+                    Some((page, _)) => asset_response(page, req.headers(), self.chunk_size),
+                    None => empty_response(StatusCode::NOT_FOUND),
+                };
+
+                future::ready(Ok(response)).boxed()
+            }
+        }
+    }
+}
+
+/// Whether any embedded asset is inside `path`, so it's a directory.
+fn is_directory(assets: &EmbeddedAssets, path: &str) -> bool {
+    let path = path.trim_matches('/');
+    assets.assets.iter().any(|asset| {
+        asset
+            .path
+            .strip_prefix(path)
+            .is_some_and(|rest| rest.starts_with('/'))
+    })
+}
+
+/// Serves the not found page. Its ETag belongs to the page rather than
+/// the requested URL, so the response is never cached or answered with a 304.
+fn not_found_page_response(
+    page: &'static EmbeddedAsset,
+    headers: &HeaderMap,
+    chunk_size: usize,
+) -> Response<UnsyncBoxBody<Bytes, std::io::Error>> {
+    let mut headers = headers.clone();
+    headers.remove(header::IF_NONE_MATCH);
+    headers.remove(header::RANGE);
+
+    let mut response = asset_response(page, &headers, chunk_size);
+    *response.status_mut() = StatusCode::NOT_FOUND;
+    response.headers_mut().remove(header::ETAG);
+    response
+}
+
+fn empty_response(status: StatusCode) -> Response<UnsyncBoxBody<Bytes, std::io::Error>> {
+    let body = Empty::new().map_err(|err| match err {}).boxed_unsync();
+    Response::builder().status(status).body(body).unwrap()
+}
+
+fn asset_response(
+    asset: &'static EmbeddedAsset,
+    headers: &HeaderMap,
+    chunk_size: usize,
+) -> Response<UnsyncBoxBody<Bytes, std::io::Error>> {
+    // Ranges are of the original content, so they're served without an encoding.
+    let range = byte_range(headers, asset.etag, asset.content.len());
+    let variant = match range {
+        ByteRange::Full => preferred_variant(asset, headers),
+        _ => None,
+    };
+    let (etag, content) = match variant {
+        Some(variant) => (variant.etag, variant.content),
+        None => (asset.etag, asset.content),
+    };
+
+    let mut builder = Response::builder()
+        .header(header::ETAG, etag)
+        .header(header::CACHE_CONTROL, asset.cache_control_header())
+        .header(header::ACCEPT_RANGES, "bytes");
+
+    // Caches need to know the response depends on the encodings the client accepts.
+    if !asset.encodings.is_empty() {
+        builder = builder.header(header::VARY, header::ACCEPT_ENCODING.as_str());
+    }
+
+    let if_none_match = headers.get_all(header::IF_NONE_MATCH).iter();
+    if etag_matches(if_none_match.filter_map(|value| value.to_str().ok()), etag) {
+        let body = Empty::new().map_err(|err| match err {}).boxed_unsync();
+        return builder.status(StatusCode::NOT_MODIFIED).body(body).unwrap();
+    }
+
+    if let Some(variant) = variant {
+        builder = builder.header(header::CONTENT_ENCODING, variant.encoding);
+    }
+
+    if let Some(content_disposition) = asset.content_disposition {
+        builder = builder.header(header::CONTENT_DISPOSITION, content_disposition);
+    }
+
+    let content = match range {
+        ByteRange::Full => content,
+        ByteRange::Partial(range) => {
+            let content_range = format!("bytes {}-{}/{}", range.start, range.end - 1, content.len());
+            builder = builder
+                .status(StatusCode::PARTIAL_CONTENT)
+                .header(header::CONTENT_RANGE, content_range);
+            &content[range]
+        }
+        ByteRange::Unsatisfiable => {
+            let body = Empty::new().map_err(|err| match err {}).boxed_unsync();
+            return builder
+                .status(StatusCode::RANGE_NOT_SATISFIABLE)
+                .header(header::CONTENT_RANGE, format!("bytes */{}", content.len()))
+                .body(body)
+                .unwrap();
+        }
+    };
+
+    let body = ChunkedBody {
+        content,
+        chunk_size,
+    }
+    .map_err(|err| match err {})
+    .boxed_unsync();
+
+    builder
+        .header(header::CONTENT_TYPE, asset.mime)
+        .body(body)
+        .unwrap()
+}
+
+/// A body that yields static content in frames of at most `chunk_size` bytes.
+struct ChunkedBody {
+    content: &'static [u8],
+    chunk_size: usize,
+}
+
+impl Body for ChunkedBody {
+    type Data = Bytes;
+    type Error = Infallible;
+
+    fn poll_data(
+        mut self: Pin<&mut Self>,
+        _cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Self::Data, Self::Error>>> {
+        if self.content.is_empty() {
+            return Poll::Ready(None);
+        }
+
+        let (chunk, rest) = self.content.split_at(self.chunk_size.min(self.content.len()));
+        self.content = rest;
+
+        Poll::Ready(Some(Ok(Bytes::from_static(chunk))))
+    }
+
+    fn poll_trailers(
+        self: Pin<&mut Self>,
+        _cx: &mut Context<'_>,
+    ) -> Poll<Result<Option<HeaderMap>, Self::Error>> {
+        Poll::Ready(Ok(None))
+    }
+
+    fn is_end_stream(&self) -> bool {
+        self.content.is_empty()
+    }
+
+    fn size_hint(&self) -> SizeHint {
+        SizeHint::with_exact(self.content.len() as u64)
+    }
+}
+
+/// The part of an asset a request asks for with a `Range` header.
+#[derive(Debug, PartialEq, Eq)]
+enum ByteRange {
+    /// The whole content, when there's no `Range` header, or it can't be used.
+    Full,
+    /// The bytes in this range, which is within the content.
+    Partial(Range<usize>),
+    /// A range that starts past the end of the content.
+    Unsatisfiable,
+}
+
+/// Reads the `Range` header, for content of `len` bytes with the given ETag.
+///
+/// Only a single range is supported, since more need a multipart response.
+/// Those, invalid ranges, and an `If-Range` that doesn't match the ETag,
+/// get the whole content, like the header isn't there.
+fn byte_range(headers: &HeaderMap, etag: &str, len: usize) -> ByteRange {
+    let Some(range) = headers.get(header::RANGE).and_then(|value| value.to_str().ok()) else {
+        return ByteRange::Full;
+    };
+
+    // If-Range needs a strong match. There's no Last-Modified, so a date never matches.
+    if let Some(if_range) = headers.get(header::IF_RANGE) {
+        if if_range.to_str().ok().map(str::trim) != Some(etag) {
+            return ByteRange::Full;
+        }
+    }
+
+    let Some((start, end)) = range
+        .trim()
+        .strip_prefix("bytes=")
+        .filter(|spec| !spec.contains(','))
+        .and_then(|spec| spec.split_once('-'))
+    else {
+        return ByteRange::Full;
+    };
+
+    let range = match (start.trim(), end.trim()) {
+        // The last `suffix` bytes.
+        ("", suffix) => match suffix.parse::<usize>() {
+            Ok(0) => return ByteRange::Unsatisfiable,
+            Ok(suffix) => len.saturating_sub(suffix)..len,
+            Err(_) => return ByteRange::Full,
+        },
+        (start, "") => match start.parse() {
+            Ok(start) => start..len,
+            Err(_) => return ByteRange::Full,
+        },
+        // The end is inclusive, and can be past the end of the content.
+        (start, end) => match (start.parse::<usize>(), end.parse::<usize>()) {
+            (Ok(start), Ok(end)) if start <= end => start..end.saturating_add(1).min(len),
+            _ => return ByteRange::Full,
+        },
+    };
+
+    if range.start >= len {
+        ByteRange::Unsatisfiable
+    } else {
+        ByteRange::Partial(range)
+    }
+}
+
+/// Picks the precompressed variant the client ranks highest in `Accept-Encoding`,
+/// preferring the server's order on ties. `None` means the original content.
+fn preferred_variant(
+    asset: &'static EmbeddedAsset,
+    headers: &HeaderMap,
+) -> Option<&'static EncodedVariant> {
+    let mut preferred: Option<(&'static EncodedVariant, f32)> = None;
+
+    for variant in asset.encodings {
+        let quality = encoding_quality(headers, variant.encoding);
+
+        if quality > 0.0 && preferred.is_none_or(|(_, best)| quality > best) {
+            preferred = Some((variant, quality));
+        }
+    }
+
+    preferred.map(|(variant, _)| variant)
+}
+
+/// The quality value (`q`) the client gave an encoding in `Accept-Encoding`,
+/// falling back to the `*` entry. Encodings that aren't listed get 0.
+fn encoding_quality(headers: &HeaderMap, encoding: &str) -> f32 {
+    let mut wildcard = 0.0;
+
+    let entries = headers
+        .get_all(header::ACCEPT_ENCODING)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','));
+
+    for entry in entries {
+        let mut params = entry.split(';');
+        let name = params.next().unwrap_or_default().trim();
+        let quality = params
+            .find_map(|param| param.trim().strip_prefix("q="))
+            .and_then(|quality| quality.trim().parse().ok())
+            .unwrap_or(1.0);
+
+        if name.eq_ignore_ascii_case(encoding) {
+            return quality;
+        }
+
+        if name == "*" {
+            wildcard = quality;
+        }
+    }
+
+    wildcard
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    static CONTENT: &[u8] = b"0123456789abcdefghij";
+
+    static ASSETS: &[EmbeddedAsset] = &[
+        asset("data.txt", "text/plain", CONTENT),
+        asset("index.html", "text/html", b"home"),
+        asset("docs/index.html", "text/html", b"docs"),
+        // The bundler applies mime overrides before embedding.
+        asset("site.webmanifest", "application/manifest+json", b"{}"),
+        asset("404.html", "text/html", b"not found"),
+    ];
+
+    const fn asset(
+        path: &'static str,
+        mime: &'static str,
+        content: &'static [u8],
+    ) -> EmbeddedAsset {
+        EmbeddedAsset {
+            path,
+            mime,
+            etag: "\"data\"",
+            immutable: false,
+            cache_control: None,
+            content_disposition: None,
+            content,
+            encodings: &[],
+        }
+    }
+
+    fn headers(pairs: &[(header::HeaderName, &'static str)]) -> HeaderMap {
+        pairs
+            .iter()
+            .map(|(name, value)| (name.clone(), HeaderValue::from_static(value)))
+            .collect()
+    }
+
+    /// The range `range` asks for of 20 bytes with the ETag `"data"`.
+    fn range(range: &'static str) -> ByteRange {
+        byte_range(&headers(&[(header::RANGE, range)]), "\"data\"", 20)
+    }
+
+    /// Like [`range`], with an `If-Range` header.
+    fn if_range(range: &'static str, if_range: &'static str) -> ByteRange {
+        let headers = headers(&[(header::RANGE, range), (header::IF_RANGE, if_range)]);
+        byte_range(&headers, "\"data\"", 20)
+    }
+
+    #[test]
+    fn single_range() {
+        assert_eq!(range("bytes=0-9"), ByteRange::Partial(0..10));
+        assert_eq!(range("bytes=15-"), ByteRange::Partial(15..20));
+        // The end is clamped to the content.
+        assert_eq!(range("bytes=10-99"), ByteRange::Partial(10..20));
+    }
+
+    #[test]
+    fn suffix_range() {
+        assert_eq!(range("bytes=-5"), ByteRange::Partial(15..20));
+        assert_eq!(range("bytes=-50"), ByteRange::Partial(0..20));
+        assert_eq!(range("bytes=-0"), ByteRange::Unsatisfiable);
+    }
+
+    #[test]
+    fn out_of_bounds_range() {
+        assert_eq!(range("bytes=20-"), ByteRange::Unsatisfiable);
+        assert_eq!(range("bytes=30-40"), ByteRange::Unsatisfiable);
+    }
+
+    #[test]
+    fn unsupported_ranges_get_everything() {
+        let headers = HeaderMap::new();
+        assert_eq!(byte_range(&headers, "\"data\"", 20), ByteRange::Full);
+        assert_eq!(range("bytes=0-1,4-5"), ByteRange::Full);
+        assert_eq!(range("bytes=9-3"), ByteRange::Full);
+        assert_eq!(range("items=0-9"), ByteRange::Full);
+    }
+
+    #[test]
+    fn if_range_mismatch() {
+        assert_eq!(if_range("bytes=0-9", "\"data\""), ByteRange::Partial(0..10));
+        assert_eq!(if_range("bytes=0-9", "\"old\""), ByteRange::Full);
+        // If-Range needs a strong match, and there's no Last-Modified for dates.
+        assert_eq!(if_range("bytes=0-9", "W/\"data\""), ByteRange::Full);
+        let date = "Wed, 21 Oct 2015 07:28:00 GMT";
+        assert_eq!(if_range("bytes=0-9", date), ByteRange::Full);
+    }
+
+    /// Requests `data.txt`, with a `Range` header if there's a range.
+    async fn get(range: Option<&str>) -> (StatusCode, HeaderMap, Vec<u8>) {
+        let mut request = Request::get("/data.txt");
+        if let Some(range) = range {
+            request = request.header(header::RANGE, range);
+        }
+
+        let service = CremeReleaseService::new(EmbeddedAssets::new(ASSETS)).chunk_size(4);
+        call(service, request.body(Empty::new()).unwrap()).await
+    }
+
+    async fn call(
+        service: CremeReleaseService,
+        request: Request<Empty<Bytes>>,
+    ) -> (StatusCode, HeaderMap, Vec<u8>) {
+        let response = service.oneshot(request).await.unwrap();
+        let (parts, mut body) = response.into_parts();
+
+        let mut content = Vec::new();
+        while let Some(chunk) = body.data().await {
+            content.extend_from_slice(&chunk.unwrap());
+        }
+
+        (parts.status, parts.headers, content)
+    }
+
+    #[tokio::test]
+    async fn range_responses() {
+        let (status, headers, content) = get(Some("bytes=2-11")).await;
+        assert_eq!(status, StatusCode::PARTIAL_CONTENT);
+        assert_eq!(headers[header::CONTENT_RANGE], "bytes 2-11/20");
+        assert_eq!(content, b"23456789ab");
+
+        let (status, headers, content) = get(Some("bytes=-3")).await;
+        assert_eq!(status, StatusCode::PARTIAL_CONTENT);
+        assert_eq!(headers[header::CONTENT_RANGE], "bytes 17-19/20");
+        assert_eq!(content, b"hij");
+
+        let (status, headers, content) = get(Some("bytes=20-")).await;
+        assert_eq!(status, StatusCode::RANGE_NOT_SATISFIABLE);
+        assert_eq!(headers[header::CONTENT_RANGE], "bytes */20");
+        assert!(content.is_empty());
+
+        let (status, headers, content) = get(None).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(headers[header::ACCEPT_RANGES], "bytes");
+        assert!(!headers.contains_key(header::CONTENT_RANGE));
+        assert_eq!(content, CONTENT);
+    }
+
+    async fn get_path(
+        service: &CremeReleaseService,
+        path: &str,
+    ) -> (StatusCode, HeaderMap, Vec<u8>) {
+        let request = Request::get(path).body(Empty::new()).unwrap();
+        call(service.clone(), request).await
+    }
+
+    #[tokio::test]
+    async fn directories_serve_their_index() {
+        let service = CremeReleaseService::new(EmbeddedAssets::new(ASSETS));
+
+        let (status, _, content) = get_path(&service, "/").await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(content, b"home");
+
+        let (status, _, content) = get_path(&service, "/docs/").await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(content, b"docs");
+
+        let (status, headers, _) = get_path(&service, "/docs?page=2").await;
+        assert_eq!(status, StatusCode::TEMPORARY_REDIRECT);
+        assert_eq!(headers[header::LOCATION], "/docs/?page=2");
+
+        let service = service.append_index_html(false);
+        assert_eq!(get_path(&service, "/").await.0, StatusCode::NOT_FOUND);
+        assert_eq!(get_path(&service, "/docs/").await.0, StatusCode::NOT_FOUND);
+        let (status, _, content) = get_path(&service, "/docs/index.html").await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(content, b"docs");
+    }
+
+    #[tokio::test]
+    async fn embedded_mime_types_are_served() {
+        let service = CremeReleaseService::new(EmbeddedAssets::new(ASSETS));
+
+        let (status, headers, _) = get_path(&service, "/site.webmanifest").await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(headers[header::CONTENT_TYPE], "application/manifest+json");
+    }
+
+    #[tokio::test]
+    async fn unknown_assets_under_the_prefix_are_empty() {
+        let service = CremeReleaseService::new(EmbeddedAssets::new(ASSETS))
+            .not_found_page("404.html")
+            .assets_prefix("/static/v1");
+
+        let (status, _, content) = get_path(&service, "/static/v1/missing.js").await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        assert!(content.is_empty());
+
+        // With another prefix, `/assets` is just a page.
+        let (status, _, content) = get_path(&service, "/assets/missing.js").await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        assert_eq!(content, b"not found");
+    }
+}
@@ -0,0 +1,206 @@
+use std::{
+    borrow::Cow,
+    convert::Infallible,
+    path::PathBuf,
+    task::{Context, Poll},
+};
+
+use bytes::Bytes;
+use http::{Request, Response};
+use http_body::combinators::UnsyncBoxBody;
+use mime::Mime;
+use tower::Service;
+use tower_http::services::fs::DefaultServeDirFallback;
+
+use super::{CremeDevService, CremeReleaseService};
+use crate::embed::EmbeddedAssets;
+
+/// Serves assets either from disk, like [`CremeDevService`], or embedded in the
+/// binary, like [`CremeReleaseService`], picked when the program starts.
+/// This lets a single release build serve its assets from disk, like on staging.
+///
+/// `creme::runtime_service!()` sets this up from the `CREME_RELEASE_MODE`
+/// env var, see [`CremeService::from_env`].
+#[derive(Clone)]
+// Services are cloned for every request, so boxing the disk service would
+// only add an allocation to each of those clones.
+#[allow(clippy::large_enum_variant)]
+pub enum CremeService<F = DefaultServeDirFallback> {
+    Disk(CremeDevService<F>),
+    Embedded(CremeReleaseService<F>),
+}
+
+impl CremeService {
+    /// Serves assets from disk.
+    pub fn disk(assets_dir: impl Into<PathBuf>, public_dir: impl Into<PathBuf>) -> Self {
+        Self::Disk(CremeDevService::new(assets_dir.into(), public_dir.into()))
+    }
+
+    /// Serves assets embedded by `creme_bundler`.
+    pub fn embedded(assets: EmbeddedAssets) -> Self {
+        Self::Embedded(CremeReleaseService::new(assets))
+    }
+
+    /// Picks how to serve assets from the `CREME_RELEASE_MODE` env var, read
+    /// when this is called. `development` serves them from disk, and `release`,
+    /// or no env var, serves the embedded assets if there are any.
+    /// `cargo run` sets the env var to the mode the assets were bundled in.
+    ///
+    /// From disk, assets are read from the `CREME_ASSETS_DIR` and
+    /// `CREME_PUBLIC_DIR` env vars if they're set, so a deployed binary can
+    /// point at a copy of the bundler's output, or `assets_dir` and `public_dir`
+    /// otherwise. Those are where the bundler wrote its output in release mode.
+    ///
+    /// # Panics
+    ///
+    /// This will panic if `CREME_RELEASE_MODE` is set to anything else.
+    pub fn from_env(
+        embedded: Option<EmbeddedAssets>,
+        assets_dir: impl Into<PathBuf>,
+        public_dir: impl Into<PathBuf>,
+    ) -> Self {
+        let dir_from_env = |name: &str, default: PathBuf| {
+            std::env::var_os(name).map(PathBuf::from).unwrap_or(default)
+        };
+        let disk = || {
+            Self::disk(
+                dir_from_env("CREME_ASSETS_DIR", assets_dir.into()),
+                dir_from_env("CREME_PUBLIC_DIR", public_dir.into()),
+            )
+        };
+
+        match (std::env::var("CREME_RELEASE_MODE").ok().as_deref(), embedded) {
+            (Some("release") | None, Some(assets)) => Self::embedded(assets),
+            (Some("release" | "development") | None, _) => disk(),
+            (Some(mode), _) => panic!("unknown CREME_RELEASE_MODE {mode}"),
+        }
+    }
+
+    /// Whether the assets are served from the binary, rather than from disk.
+    pub fn is_embedded(&self) -> bool {
+        matches!(self, Self::Embedded(_))
+    }
+
+    /// Serves a page from the public directory, like `404.html`, with a 404 status
+    /// when nothing matches the request. See [`CremeDevService::not_found_page`]
+    /// and [`CremeReleaseService::not_found_page`].
+    pub fn not_found_page(self, path: &str) -> Self {
+        match self {
+            Self::Disk(service) => Self::Disk(service.not_found_page(path)),
+            Self::Embedded(service) => Self::Embedded(service.not_found_page(path)),
+        }
+    }
+
+    /// Serves a file from the public directory, like a single-page app's
+    /// `index.html`, with a 200 status when nothing matches the request.
+    /// See [`CremeDevService::fallback_file`] and [`CremeReleaseService::fallback_file`].
+    pub fn fallback_file(self, path: &str) -> Self {
+        match self {
+            Self::Disk(service) => Self::Disk(service.fallback_file(path)),
+            Self::Embedded(service) => Self::Embedded(service.fallback_file(path)),
+        }
+    }
+
+    /// Serves assets from `overlay_dir` before the assets directory, when they're
+    /// served from disk. See [`CremeDevService::overlay_dir`].
+    pub fn overlay_dir(self, overlay_dir: PathBuf) -> Self {
+        match self {
+            Self::Disk(service) => Self::Disk(service.overlay_dir(overlay_dir)),
+            service => service,
+        }
+    }
+
+    /// Sets a service to call when no file matches the request.
+    pub fn fallback<F2>(self, new_fallback: F2) -> CremeService<F2>
+    where
+        F2: Clone,
+    {
+        match self {
+            Self::Disk(service) => CremeService::Disk(service.fallback(new_fallback)),
+            Self::Embedded(service) => CremeService::Embedded(service.fallback(new_fallback)),
+        }
+    }
+}
+
+impl<F> CremeService<F> {
+    /// Serves files with the given extension with this mime type, when they're
+    /// served from disk. Embedded assets already have the bundler's overrides applied.
+    pub fn mime_override(self, ext: impl AsRef<str>, mime: Mime) -> Self {
+        match self {
+            Self::Disk(service) => Self::Disk(service.mime_override(ext, mime)),
+            service => service,
+        }
+    }
+
+    /// Serves assets under this URL prefix instead of `/assets`. See
+    /// [`CremeDevService::assets_prefix`] and [`CremeReleaseService::assets_prefix`].
+    pub fn assets_prefix(self, prefix: impl Into<Cow<'static, str>>) -> Self {
+        match self {
+            Self::Disk(service) => Self::Disk(service.assets_prefix(prefix)),
+            Self::Embedded(service) => Self::Embedded(service.assets_prefix(prefix)),
+        }
+    }
+
+    /// Sets the largest body frame an asset is served in, when they're embedded.
+    /// See [`CremeReleaseService::chunk_size`].
+    pub fn chunk_size(self, bytes: usize) -> Self {
+        match self {
+            Self::Embedded(service) => Self::Embedded(service.chunk_size(bytes)),
+            service => service,
+        }
+    }
+
+    /// Sets the body of responses to files that couldn't be read, when they're
+    /// served from disk. See [`CremeDevService::with_error_body`].
+    pub fn with_error_body(self, body: impl Into<Bytes>) -> Self {
+        match self {
+            Self::Disk(service) => Self::Disk(service.with_error_body(body)),
+            service => service,
+        }
+    }
+
+    /// Serves `index.html` for directory paths. Enabled by default.
+    /// See [`CremeDevService::append_index_html`] and [`CremeReleaseService::append_index_html`].
+    pub fn append_index_html(self, append_index_html: bool) -> Self {
+        match self {
+            Self::Disk(service) => Self::Disk(service.append_index_html(append_index_html)),
+            Self::Embedded(service) => {
+                Self::Embedded(service.append_index_html(append_index_html))
+            }
+        }
+    }
+}
+
+impl<ReqBody, F> Service<Request<ReqBody>> for CremeService<F>
+where
+    CremeDevService<F>: Service<
+        Request<ReqBody>,
+        Response = Response<UnsyncBoxBody<Bytes, std::io::Error>>,
+        Error = Infallible,
+    >,
+    CremeReleaseService<F>: Service<
+        Request<ReqBody>,
+        Response = Response<UnsyncBoxBody<Bytes, std::io::Error>>,
+        Error = Infallible,
+        Future = <CremeDevService<F> as Service<Request<ReqBody>>>::Future,
+    >,
+{
+    type Response = Response<UnsyncBoxBody<Bytes, std::io::Error>>;
+    type Error = Infallible;
+    type Future = <CremeDevService<F> as Service<Request<ReqBody>>>::Future;
+
+    #[inline]
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        match self {
+            Self::Disk(service) => service.poll_ready(cx),
+            Self::Embedded(service) => service.poll_ready(cx),
+        }
+    }
+
+    fn call(&mut self, req: Request<ReqBody>) -> Self::Future {
+        match self {
+            Self::Disk(service) => service.call(req),
+            Self::Embedded(service) => service.call(req),
+        }
+    }
+}
@@ -0,0 +1,237 @@
+use std::{
+    borrow::Cow,
+    collections::HashMap,
+    fs, io,
+    path::{Component, Path, PathBuf},
+};
+
+use mime::Mime;
+
+use crate::embed::{EmbeddedAsset, EmbeddedAssets};
+
+/// The URL prefix assets are served under by default.
+pub(crate) const ASSETS_PREFIX: &str = "/assets";
+
+/// Strips `prefix`, like `/assets`, from a request path (and query), returning
+/// the rest of it, or `None` if the request isn't under it.
+/// `/assetsfoo` doesn't match `/assets`.
+pub(crate) fn strip_prefix<'a>(path: &'a str, prefix: &str) -> Option<Cow<'a, str>> {
+    let rest = path.strip_prefix(prefix)?;
+
+    match rest.chars().next() {
+        None => Some(Cow::Borrowed("/")),
+        Some('/') => Some(Cow::Borrowed(rest)),
+        Some('?') => Some(Cow::Owned(format!("/{rest}"))),
+        _ => None,
+    }
+}
+
+/// An asset resolved from an [`AssetStore`].
+#[derive(Debug)]
+pub struct ResolvedAsset {
+    pub mime: Mime,
+    pub content: Cow<'static, [u8]>,
+}
+
+/// Resolves request paths to assets, without depending on any HTTP framework.
+///
+/// Requests under the assets prefix, `/assets` by default, are looked up in
+/// the assets directory, and everything else in the public directory. This is
+/// useful for integrating creme with servers that don't use tower, and it's
+/// what [`CremeDevService`](crate::services::CremeDevService) and
+/// [`CremeReleaseService`](crate::services::CremeReleaseService) route with.
+#[derive(Debug, Clone)]
+pub struct AssetStore {
+    source: Source,
+    /// The URL prefix assets are served under, like `/assets`.
+    assets_prefix: Cow<'static, str>,
+    /// Mime types to use instead of guessing, by lowercase file extension.
+    mime_overrides: HashMap<String, Mime>,
+}
+
+#[derive(Debug, Clone)]
+enum Source {
+    /// Assets are read from disk on every request.
+    Directory {
+        assets_dir: PathBuf,
+        public_dir: PathBuf,
+    },
+    /// Assets are embedded in the binary.
+    Embedded(EmbeddedAssets),
+}
+
+impl AssetStore {
+    pub fn from_dirs(assets_dir: impl Into<PathBuf>, public_dir: impl Into<PathBuf>) -> Self {
+        Self::new(Source::Directory {
+            assets_dir: assets_dir.into(),
+            public_dir: public_dir.into(),
+        })
+    }
+
+    pub fn embedded(assets: EmbeddedAssets) -> Self {
+        Self::new(Source::Embedded(assets))
+    }
+
+    fn new(source: Source) -> Self {
+        Self {
+            source,
+            assets_prefix: Cow::Borrowed(ASSETS_PREFIX),
+            mime_overrides: HashMap::new(),
+        }
+    }
+
+    /// Looks assets up under this URL prefix instead of `/assets`, like `/static`
+    /// for `/static/img/cat.jpeg`. The slashes around it are optional.
+    ///
+    /// # Panics
+    ///
+    /// This will panic if the prefix is empty, like `/`, since assets would
+    /// shadow every public file.
+    pub fn assets_prefix(self, prefix: impl Into<Cow<'static, str>>) -> Self {
+        let prefix = prefix.into();
+        let trimmed = prefix.trim_matches('/');
+        assert!(!trimmed.is_empty(), "the assets prefix can't be empty");
+
+        Self {
+            assets_prefix: if prefix.starts_with('/') && !prefix.ends_with('/') {
+                prefix
+            } else {
+                Cow::Owned(format!("/{trimmed}"))
+            },
+            ..self
+        }
+    }
+
+    /// Overrides the mime type for files with the given extension.
+    /// Embedded assets already have their overrides applied by the bundler,
+    /// so this only affects assets read from disk.
+    pub fn mime_override(mut self, ext: impl AsRef<str>, mime: Mime) -> Self {
+        let ext = ext.as_ref().trim_start_matches('.').to_lowercase();
+        self.mime_overrides.insert(ext, mime);
+        self
+    }
+
+    /// Applies the overrides `creme_bundler` passes on through `CREME_MIME_OVERRIDES`.
+    /// Used by [`asset_store!`](crate::asset_store!).
+    #[doc(hidden)]
+    pub fn mime_overrides_from_env(self, overrides: Option<&str>) -> Self {
+        let overrides: HashMap<String, String> = overrides
+            .and_then(|overrides| serde_json::from_str(overrides).ok())
+            .unwrap_or_default();
+
+        overrides
+            .into_iter()
+            .filter_map(|(ext, mime)| Some((ext, mime.parse().ok()?)))
+            .fold(self, |store, (ext, mime)| store.mime_override(ext, mime))
+    }
+
+    /// Resolves a request path, like `/assets/style.css` or `/robots.txt`.
+    ///
+    /// Returns `Ok(None)` if there is no asset at that path.
+    ///
+    /// # Errors
+    ///
+    /// This will return an error if the file exists but couldn't be read.
+    pub fn get(&self, path: &str) -> io::Result<Option<ResolvedAsset>> {
+        match &self.source {
+            Source::Directory {
+                assets_dir,
+                public_dir,
+            } => {
+                let (dir, path) = match self.strip_assets_prefix(path) {
+                    Some(rest) => (assets_dir, rest.into_owned()),
+                    None => (public_dir, path.to_string()),
+                };
+
+                let Some(file_path) = join_request_path(dir, &path) else {
+                    return Ok(None);
+                };
+
+                match fs::read(&file_path) {
+                    Ok(content) => Ok(Some(ResolvedAsset {
+                        mime: self.mime_override_for(&path).cloned().unwrap_or_else(|| {
+                            mime_guess::from_path(&file_path).first_or_octet_stream()
+                        }),
+                        content: Cow::Owned(content),
+                    })),
+                    Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(None),
+                    Err(err) => Err(err),
+                }
+            }
+            Source::Embedded(_) => Ok(self.find_embedded(path).map(|asset| ResolvedAsset {
+                mime: asset.mime.parse().unwrap_or(mime::APPLICATION_OCTET_STREAM),
+                content: Cow::Borrowed(asset.content),
+            })),
+        }
+    }
+
+    /// Strips the assets prefix from a request path (and query), returning the
+    /// rest of it, or `None` if the request isn't for an asset.
+    pub(crate) fn strip_assets_prefix<'a>(&self, path: &'a str) -> Option<Cow<'a, str>> {
+        strip_prefix(path, &self.assets_prefix)
+    }
+
+    /// The mime override for the file a request path points at, if there is one.
+    pub(crate) fn mime_override_for(&self, path: &str) -> Option<&Mime> {
+        let (_, ext) = path.rsplit('/').next()?.rsplit_once('.')?;
+        self.mime_overrides.get(&ext.to_lowercase())
+    }
+
+    /// Finds an embedded asset by its URL path. Always `None` when the assets
+    /// are read from disk.
+    pub(crate) fn find_embedded(&self, path: &str) -> Option<&'static EmbeddedAsset> {
+        self.embedded_assets().find(path)
+    }
+
+    /// The embedded assets, or none when the assets are read from disk.
+    pub(crate) fn embedded_assets(&self) -> EmbeddedAssets {
+        match &self.source {
+            Source::Directory { .. } => EmbeddedAssets::new(&[]),
+            Source::Embedded(assets) => *assets,
+        }
+    }
+}
+
+/// Joins a request path onto a directory, rejecting anything that could
+/// escape it (like `..`).
+fn join_request_path(dir: &Path, path: &str) -> Option<PathBuf> {
+    let mut file_path = dir.to_path_buf();
+
+    for component in Path::new(path.trim_start_matches('/')).components() {
+        match component {
+            Component::Normal(segment) => file_path.push(segment),
+            Component::CurDir => {}
+            _ => return None,
+        }
+    }
+
+    file_path.is_file().then_some(file_path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn directory_store_uses_its_prefix_and_overrides() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir_all(dir.path().join("static")).unwrap();
+        fs::create_dir_all(dir.path().join("public")).unwrap();
+        fs::write(dir.path().join("static/site.webmanifest"), "{}").unwrap();
+        fs::write(dir.path().join("public/robots.txt"), "User-agent: *").unwrap();
+
+        let store = AssetStore::from_dirs(dir.path().join("static"), dir.path().join("public"))
+            .assets_prefix("static/")
+            .mime_override(".webmanifest", "application/manifest+json".parse().unwrap());
+
+        let asset = store.get("/static/site.webmanifest").unwrap().unwrap();
+        assert_eq!(asset.mime.as_ref(), "application/manifest+json");
+        assert_eq!(&*asset.content, b"{}");
+
+        let page = store.get("/robots.txt").unwrap().unwrap();
+        assert_eq!(page.mime, mime::TEXT_PLAIN);
+
+        assert!(store.get("/assets/site.webmanifest").unwrap().is_none());
+        assert!(store.get("/static/../public/robots.txt").unwrap().is_none());
+    }
+}
@@ -0,0 +1,113 @@
+//! Bundles assets without a build script, like for a frontend that isn't
+//! built with Cargo, and prints the manifest.
+
+use std::{
+    error::Error,
+    fs,
+    io::{self, Write},
+    path::PathBuf,
+    process::ExitCode,
+};
+
+use creme_bundler::{ConfigReleaseMode, Creme, CremeConfig};
+
+const USAGE: &str = "\
+Usage: creme [OPTIONS]
+
+Bundles the assets and prints the manifest.
+
+Options:
+  --config <FILE>  Reads options from a creme.toml or creme.json file
+  --assets <DIR>   The assets directory [default: assets]
+  --public <DIR>   The public directory [default: public]
+  --out <DIR>      The output directory [default: target/creme]
+  --release        Bundles in release mode (the default)
+  --dev            Bundles in development mode
+  -h, --help       Prints this help";
+
+/// Where the output goes without `--out`, or an `out_dir` in the config.
+const DEFAULT_OUT_DIR: &str = "target/creme";
+
+#[derive(Default)]
+struct Args {
+    config: Option<PathBuf>,
+    assets: Option<PathBuf>,
+    public: Option<PathBuf>,
+    out: Option<PathBuf>,
+    release_mode: Option<ConfigReleaseMode>,
+}
+
+impl Args {
+    /// Parses the command line. `None` if only the help was asked for.
+    fn parse(mut args: impl Iterator<Item = String>) -> Result<Option<Self>, String> {
+        let mut parsed = Args::default();
+
+        while let Some(arg) = args.next() {
+            let mut value = || {
+                args.next()
+                    .map(PathBuf::from)
+                    .ok_or_else(|| format!("{arg} needs a value"))
+            };
+
+            match arg.as_str() {
+                "--config" => parsed.config = Some(value()?),
+                "--assets" => parsed.assets = Some(value()?),
+                "--public" => parsed.public = Some(value()?),
+                "--out" => parsed.out = Some(value()?),
+                "--release" => parsed.release_mode = Some(ConfigReleaseMode::Release),
+                "--dev" => parsed.release_mode = Some(ConfigReleaseMode::Development),
+                "-h" | "--help" => return Ok(None),
+                _ => return Err(format!("unknown argument {arg}\n\n{USAGE}")),
+            }
+        }
+
+        Ok(Some(parsed))
+    }
+}
+
+fn main() -> ExitCode {
+    match run() {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("creme: {err}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn run() -> Result<(), Box<dyn Error>> {
+    let Some(args) = Args::parse(std::env::args().skip(1))? else {
+        println!("{USAGE}");
+        return Ok(());
+    };
+
+    let mut config = match &args.config {
+        Some(path) => CremeConfig::from_file(path)?,
+        None => CremeConfig::default(),
+    };
+
+    // Flags take precedence over the config file.
+    config.assets_dir = args.assets.or(config.assets_dir);
+    config.public_dir = args.public.or(config.public_dir);
+    config.out_dir = args.out.or(config.out_dir).or(Some(DEFAULT_OUT_DIR.into()));
+
+    // `auto` would pick the mode this binary was compiled in, which says
+    // nothing about the assets, so it means release here.
+    config.release_mode = match (args.release_mode, config.release_mode) {
+        (Some(mode), _) => mode,
+        (None, ConfigReleaseMode::Auto) => ConfigReleaseMode::Release,
+        (None, mode) => mode,
+    };
+    let release = config.release_mode == ConfigReleaseMode::Release;
+
+    let bundler = config.apply(Creme::new())?.build()?;
+    let report = bundler.bundle_with_report()?;
+
+    // The manifest goes to stdout, so it can be piped into other tools.
+    if release {
+        eprintln!("{report}");
+    }
+    io::stdout().write_all(&fs::read(bundler.manifest_path())?)?;
+
+    Ok(())
+}
@@ -0,0 +1,28 @@
+use std::io;
+
+use crate::{CremeBundler, CremeResult};
+
+impl CremeBundler {
+    /// Bundles the assets without blocking the async runtime, for tools and
+    /// servers that bundle on demand. Build scripts should use `bundle`.
+    ///
+    /// The whole bundle runs as one blocking task on purpose, rather than using
+    /// `tokio::fs` and only moving CSS processing to `spawn_blocking`. Most of the
+    /// time goes to CSS processing, hashing and compression, which are CPU-bound,
+    /// and `tokio::fs` runs every call on the blocking pool anyway, so awaiting each
+    /// read and write would add a task per file without freeing the runtime any
+    /// more. The file operations are also interleaved with processing, so
+    /// splitting them out would take an async copy of the bundling pipeline.
+    ///
+    /// # Errors
+    ///
+    /// This will return the same errors as `bundle`, or an io error if the
+    /// task doesn't finish, like when the runtime is shutting down.
+    pub async fn bundle_async(&self) -> CremeResult<()> {
+        let bundler = self.clone();
+
+        tokio::task::spawn_blocking(move || bundler.bundle())
+            .await
+            .map_err(io::Error::other)?
+    }
+}
@@ -0,0 +1,38 @@
+use globset::GlobMatcher;
+
+use crate::{CremeError, CremeResult};
+
+/// `Cache-Control` values for the served files matching each glob.
+/// The first matching rule wins.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct CacheControlRules {
+    rules: Vec<(GlobMatcher, String)>,
+}
+
+impl CacheControlRules {
+    /// Compiles `(glob, value)` rules, checking each value is a valid header value.
+    pub(crate) fn new(rules: Vec<(String, String)>) -> CremeResult<Self> {
+        let rules = rules
+            .into_iter()
+            .map(|(glob, value)| {
+                // Header values are visible ASCII, spaces and tabs.
+                let is_valid = |b: u8| b == b'\t' || (b' '..=b'~').contains(&b);
+                if value.is_empty() || !value.bytes().all(is_valid) {
+                    return Err(CremeError::InvalidCacheControl(value));
+                }
+
+                Ok((crate::glob(&glob)?.compile_matcher(), value))
+            })
+            .collect::<CremeResult<_>>()?;
+
+        Ok(Self { rules })
+    }
+
+    /// The value for a file, by the URL it's served at, like `robots.txt`.
+    pub(crate) fn get(&self, url: &str) -> Option<&str> {
+        self.rules
+            .iter()
+            .find(|(glob, _)| glob.is_match(url))
+            .map(|(_, value)| value.as_str())
+    }
+}
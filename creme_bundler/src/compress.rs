@@ -0,0 +1,117 @@
+use std::{
+    ffi::OsString,
+    fs,
+    io::{self, Write},
+    path::{Path, PathBuf},
+};
+
+use flate2::{write::GzEncoder, Compression};
+use mime::Mime;
+
+use crate::{embed, is_unchanged, set_file_mode, AssetSourceConfig, CremeResult};
+
+/// A content encoding that output files can be precompressed with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Encoding {
+    Brotli,
+    Gzip,
+}
+
+impl Encoding {
+    /// Every encoding, in the order the server prefers them.
+    pub const ALL: [Encoding; 2] = [Encoding::Brotli, Encoding::Gzip];
+
+    /// The extension of the sidecar file, like `style.css.br`.
+    pub fn extension(self) -> &'static str {
+        match self {
+            Encoding::Brotli => "br",
+            Encoding::Gzip => "gz",
+        }
+    }
+
+    /// The value of the `Content-Encoding` header.
+    pub fn content_encoding(self) -> &'static str {
+        match self {
+            Encoding::Brotli => "br",
+            Encoding::Gzip => "gzip",
+        }
+    }
+
+    /// The path of this encoding's sidecar file for `path`.
+    pub fn sidecar_path(self, path: &Path) -> PathBuf {
+        let mut sidecar = OsString::from(path.as_os_str());
+        sidecar.push(".");
+        sidecar.push(self.extension());
+        PathBuf::from(sidecar)
+    }
+
+    fn compress(self, content: &[u8]) -> io::Result<Vec<u8>> {
+        match self {
+            Encoding::Brotli => {
+                let mut compressed = Vec::new();
+                {
+                    let mut writer = brotli::CompressorWriter::new(&mut compressed, 4096, 11, 22);
+                    writer.write_all(content)?;
+                }
+                Ok(compressed)
+            }
+            Encoding::Gzip => {
+                let mut encoder = GzEncoder::new(Vec::new(), Compression::best());
+                encoder.write_all(content)?;
+                encoder.finish()
+            }
+        }
+    }
+}
+
+/// Whether a file is worth compressing. Most binary formats, like images
+/// and fonts, are already compressed.
+fn is_compressible(mime: &Mime) -> bool {
+    match (mime.type_(), mime.subtype().as_str()) {
+        (mime::TEXT, _) => true,
+        (mime::APPLICATION, "javascript" | "json" | "xml" | "wasm" | "manifest+json") => true,
+        (mime::IMAGE, "svg") => mime.suffix() == Some(mime::XML),
+        _ => false,
+    }
+}
+
+/// Writes a sidecar file for every encoding next to each compressible file
+/// in `dir`, like `style.css.br` and `style.css.gz`. Sidecars that wouldn't
+/// be smaller than the original are skipped.
+pub(crate) fn write_precompressed(
+    dir: &Path,
+    source_config: &AssetSourceConfig,
+    file_mode: Option<u32>,
+) -> CremeResult<()> {
+    let mut files = Vec::new();
+    embed::collect_files(dir, &mut files)?;
+
+    for file in files {
+        if !is_compressible(&source_config.guess_mime(&file)) {
+            continue;
+        }
+
+        let content = fs::read(&file)?;
+
+        for encoding in Encoding::ALL {
+            let compressed = encoding.compress(&content)?;
+            let sidecar = encoding.sidecar_path(&file);
+
+            if compressed.len() < content.len() {
+                if !is_unchanged(&sidecar, &compressed) {
+                    fs::write(&sidecar, compressed)?;
+                }
+                set_file_mode(&sidecar, file_mode)?;
+            } else {
+                // A sidecar left from an earlier build would be served for the new content.
+                if let Err(err) = fs::remove_file(&sidecar) {
+                    if err.kind() != io::ErrorKind::NotFound {
+                        return Err(err.into());
+                    }
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,426 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    fs,
+    path::PathBuf,
+};
+
+use serde::Deserialize;
+
+#[cfg(feature = "tailwind")]
+use crate::TailwindConfig;
+use crate::{
+    Creme, CremeError, CremeResult, Features, HashAlgorithm, HashSource, HashStrategy, ParserFlags,
+};
+
+/// Which release mode to bundle with.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ConfigReleaseMode {
+    /// Picks development or release based on `debug_assertions`.
+    #[default]
+    Auto,
+    Development,
+    Release,
+}
+
+/// A `Cache-Control` value for the served files matching a glob.
+/// See `Creme::cache_control`.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct CacheControlRule {
+    /// A glob of served paths, like `robots.txt` or `assets/**/*.woff2`.
+    pub glob: String,
+
+    /// The header value, like `public, max-age=3600`.
+    pub value: String,
+}
+
+/// A serializable mirror of the `Creme` builder, loaded from a `creme.toml`
+/// or `creme.json` file.
+///
+/// Any field left out falls back to the same default as `Creme::recommended`.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CremeConfig {
+    /// The public directory, copied as-is to the output.
+    pub public_dir: Option<PathBuf>,
+
+    /// Whether the public directory is copied to the output in release mode.
+    pub copy_public: Option<bool>,
+
+    /// Paths in the public directory that aren't copied to the output.
+    pub public_excludes: Vec<PathBuf>,
+
+    /// The assets directory, processed by the bundler.
+    pub assets_dir: Option<PathBuf>,
+
+    /// The deploy environment, which picks an overlay for the assets directory,
+    /// like `assets.staging`. Defaults to the `CREME_ENV` env var.
+    pub environment: Option<String>,
+
+    /// The public output directory, relative to `out_dir`.
+    pub out_public_dir: Option<PathBuf>,
+
+    /// The assets output directory, relative to `out_public_dir`.
+    pub out_assets_dir: Option<PathBuf>,
+
+    /// Where all generated files are written to.
+    /// Defaults to the `OUT_DIR` env var set by Cargo.
+    pub out_dir: Option<PathBuf>,
+
+    pub release_mode: ConfigReleaseMode,
+
+    /// Whether output filenames get a content hash in release mode.
+    pub hashed: Option<bool>,
+
+    /// Whether assets are written to the top of the assets directory in release mode.
+    pub flatten: Option<bool>,
+
+    /// The algorithm used to hash output filenames.
+    pub hash_algorithm: Option<HashAlgorithm>,
+
+    /// Where the hash goes in output paths.
+    pub hash_strategy: Option<HashStrategy>,
+
+    /// What output paths are hashed from.
+    pub hash_source: Option<HashSource>,
+
+    /// Where to write the manifest. Defaults to the out directory.
+    pub manifest_path: Option<PathBuf>,
+
+    /// The manifest's file name in the out directory, if `manifest_path` isn't set.
+    pub manifest_name: Option<String>,
+
+    /// Where to write a Rust module of asset URL constants, relative to the out directory.
+    pub rust_module: Option<PathBuf>,
+
+    /// Whether to rewrite asset references in public HTML files in release mode.
+    pub rewrite_html: Option<bool>,
+
+    /// Whether to minify public HTML files in release mode.
+    pub minify_html: Option<bool>,
+
+    /// Whether JSON assets are checked to parse in release mode.
+    pub validate_json: Option<bool>,
+
+    /// Whether to minify JSON assets in release mode.
+    pub minify_json: Option<bool>,
+
+    /// Whether symlinks in the assets and public directories are followed.
+    pub follow_symlinks: Option<bool>,
+
+    /// Whether the `.gitignore` in the assets directory is respected.
+    pub respect_gitignore: Option<bool>,
+
+    /// Whether files whose extension gives no mime type get it from their contents.
+    #[cfg(feature = "sniff")]
+    pub sniff_mime: Option<bool>,
+
+    /// Unix permissions to set on output files, like `0o644`.
+    pub file_mode: Option<u32>,
+
+    /// Whether to emit CSS source maps in release mode.
+    pub css_source_maps: Option<bool>,
+
+    /// Whether to minify CSS in release mode.
+    pub css_minify: Option<bool>,
+
+    /// Whether to add and remove vendor prefixes in CSS in release mode.
+    pub css_autoprefix: Option<bool>,
+
+    /// Browserslist queries used to compile CSS.
+    pub browserslist: Option<Vec<String>>,
+
+    /// Extra directories to resolve CSS `@import`s against.
+    pub css_import_paths: Option<Vec<PathBuf>>,
+
+    /// Assets smaller than this many bytes are inlined into CSS as data URIs.
+    pub css_inline_threshold: Option<usize>,
+
+    /// CSS features that are always compiled, by their `lightningcss` names,
+    /// like `["Nesting"]`.
+    pub css_include_features: Option<Vec<String>>,
+
+    /// CSS features that are never compiled, like `["Colors"]`.
+    pub css_exclude_features: Option<Vec<String>>,
+
+    /// The draft CSS syntax stylesheets may use, like `["NESTING", "CUSTOM_MEDIA"]`.
+    pub css_parser_flags: Option<Vec<String>>,
+
+    /// Mime types by file extension, used instead of guessing.
+    pub mime_overrides: HashMap<String, String>,
+
+    /// Directories in the assets directory whose files are copied without processing.
+    pub passthrough_dirs: Vec<PathBuf>,
+
+    /// A page in the public directory served for unknown routes, like `404.html`.
+    pub not_found_page: Option<PathBuf>,
+
+    /// Globs of the assets in each named group, like `icons = ["icons/*.svg"]`.
+    pub asset_groups: BTreeMap<String, Vec<String>>,
+
+    /// `Cache-Control` values for served files, in order. The first matching rule wins.
+    pub cache_control: Vec<CacheControlRule>,
+
+    /// Globs of the assets served as downloads, like `["docs/*.pdf"]`.
+    pub download_globs: Vec<String>,
+
+    /// The largest the output can be in total, in bytes, in release mode.
+    pub max_total_size: Option<u64>,
+
+    /// The largest any single output file can be, in bytes, in release mode.
+    pub max_asset_size: Option<u64>,
+
+    /// Assets smaller than this many bytes are inlined as data URIs in release mode.
+    pub inline_threshold: Option<usize>,
+
+    /// Whether assets nothing references fail the build in release mode.
+    pub deny_unreferenced: Option<bool>,
+
+    /// Whether release bundles update the dist directory in place.
+    pub incremental: Option<bool>,
+
+    /// Whether to write gzip and brotli versions of the output in release mode.
+    pub precompress: Option<bool>,
+
+    /// A stylesheet to generate with Tailwind in release mode.
+    #[cfg(feature = "tailwind")]
+    pub tailwind: Option<TailwindConfig>,
+}
+
+impl CremeConfig {
+    /// Reads a config file. The format is picked from the file extension,
+    /// either `.toml` or `.json`.
+    ///
+    /// # Errors
+    ///
+    /// This will return an error if the file can't be read or parsed.
+    pub fn from_file(path: impl Into<PathBuf>) -> CremeResult<Self> {
+        let path: PathBuf = path.into();
+        let content = fs::read_to_string(&path)?;
+
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some("toml") => Ok(basic_toml::from_str(&content)?),
+            Some("json") => Ok(serde_json::from_str(&content)?),
+            _ => Err(CremeError::UnsupportedConfigFormat(path)),
+        }
+    }
+
+    /// Applies this config on top of a `Creme` builder.
+    ///
+    /// # Errors
+    ///
+    /// This will return an error if the assets directory doesn't exist,
+    /// or if no `out_dir` is configured and `OUT_DIR` is not set.
+    pub fn apply(self, creme: Creme) -> CremeResult<Creme> {
+        let creme = match self.out_dir {
+            Some(out_dir) => creme.out_dir(out_dir),
+            None => creme.out_dir_build_rs()?,
+        };
+
+        let mut creme = match self.release_mode {
+            ConfigReleaseMode::Auto => creme.detect_release_mode(),
+            ConfigReleaseMode::Development => creme.development(),
+            ConfigReleaseMode::Release => creme.release(),
+        };
+
+        if let Some(copy_public) = self.copy_public {
+            creme = creme.copy_public(copy_public);
+        }
+
+        for path in self.public_excludes {
+            creme = creme.exclude_public(path);
+        }
+
+        if let Some(environment) = self.environment {
+            creme = creme.environment(environment);
+        }
+
+        if let Some(hashed) = self.hashed {
+            creme = creme.hashed(hashed);
+        }
+
+        if let Some(flatten) = self.flatten {
+            creme = creme.flatten(flatten);
+        }
+
+        if let Some(hash_algorithm) = self.hash_algorithm {
+            creme = creme.hash_algorithm(hash_algorithm);
+        }
+
+        if let Some(hash_strategy) = self.hash_strategy {
+            creme = creme.hash_strategy(hash_strategy);
+        }
+
+        if let Some(hash_source) = self.hash_source {
+            creme = creme.hash_source(hash_source);
+        }
+
+        if let Some(manifest_path) = self.manifest_path {
+            creme = creme.manifest_path(manifest_path);
+        }
+
+        if let Some(manifest_name) = self.manifest_name {
+            creme = creme.manifest_name(manifest_name);
+        }
+
+        if let Some(rust_module) = self.rust_module {
+            creme = creme.emit_rust_module(rust_module);
+        }
+
+        if let Some(rewrite_html) = self.rewrite_html {
+            creme = creme.rewrite_html(rewrite_html);
+        }
+
+        if let Some(minify_html) = self.minify_html {
+            creme = creme.minify_html(minify_html);
+        }
+
+        if let Some(validate_json) = self.validate_json {
+            creme = creme.validate_json(validate_json);
+        }
+
+        if let Some(minify_json) = self.minify_json {
+            creme = creme.minify_json(minify_json);
+        }
+
+        if let Some(follow_symlinks) = self.follow_symlinks {
+            creme = creme.follow_symlinks(follow_symlinks);
+        }
+
+        if let Some(respect_gitignore) = self.respect_gitignore {
+            creme = creme.respect_gitignore(respect_gitignore);
+        }
+
+        #[cfg(feature = "sniff")]
+        if let Some(sniff_mime) = self.sniff_mime {
+            creme = creme.sniff_mime(sniff_mime);
+        }
+
+        if let Some(file_mode) = self.file_mode {
+            creme = creme.file_mode(file_mode);
+        }
+
+        if let Some(source_maps) = self.css_source_maps {
+            creme = creme.css_source_maps(source_maps);
+        }
+
+        if let Some(minify) = self.css_minify {
+            creme = creme.css_minify(minify);
+        }
+
+        if let Some(autoprefix) = self.css_autoprefix {
+            creme = creme.css_autoprefix(autoprefix);
+        }
+
+        if let Some(browserslist) = self.browserslist {
+            creme = creme.browserslist(browserslist);
+        }
+
+        if let Some(import_paths) = self.css_import_paths {
+            creme = creme.css_import_paths(import_paths);
+        }
+
+        if let Some(threshold) = self.css_inline_threshold {
+            creme = creme.css_inline_threshold(threshold);
+        }
+
+        if let Some(features) = self.css_include_features {
+            creme = creme.css_include_features(flags_from_names(features, Features::from_name)?);
+        }
+
+        if let Some(features) = self.css_exclude_features {
+            creme = creme.css_exclude_features(flags_from_names(features, Features::from_name)?);
+        }
+
+        if let Some(flags) = self.css_parser_flags {
+            creme = creme.css_parser_flags(flags_from_names(flags, ParserFlags::from_name)?);
+        }
+
+        if let Some(precompress) = self.precompress {
+            creme = creme.precompress(precompress);
+        }
+
+        if let Some(not_found_page) = self.not_found_page {
+            creme = creme.not_found_page(not_found_page);
+        }
+
+        for (ext, mime) in self.mime_overrides {
+            let mime = mime
+                .parse()
+                .map_err(|_| CremeError::InvalidMime(mime.clone()))?;
+            creme = creme.mime_override(ext, mime);
+        }
+
+        for dir in self.passthrough_dirs {
+            creme = creme.passthrough_dir(dir);
+        }
+
+        if let Some(max_total_size) = self.max_total_size {
+            creme = creme.max_total_size(max_total_size);
+        }
+
+        if let Some(max_asset_size) = self.max_asset_size {
+            creme = creme.max_asset_size(max_asset_size);
+        }
+
+        if let Some(inline_threshold) = self.inline_threshold {
+            creme = creme.inline_threshold(inline_threshold);
+        }
+
+        if let Some(deny_unreferenced) = self.deny_unreferenced {
+            creme = creme.deny_unreferenced(deny_unreferenced);
+        }
+
+        if let Some(incremental) = self.incremental {
+            creme = creme.incremental(incremental);
+        }
+
+        for CacheControlRule { glob, value } in self.cache_control {
+            creme = creme.cache_control(glob, value);
+        }
+
+        creme = creme.download_globs(self.download_globs);
+
+        for (name, globs) in self.asset_groups {
+            for glob in globs {
+                creme = creme.asset_group(&name, glob);
+            }
+        }
+
+        #[cfg(feature = "tailwind")]
+        if let Some(tailwind) = self.tailwind {
+            creme = creme.tailwind(tailwind);
+        }
+
+        Ok(creme
+            .set_public_dir(self.public_dir.unwrap_or_else(|| "public".into()))
+            .set_assets_dir(self.assets_dir.unwrap_or_else(|| "assets".into()))?
+            .set_out_public_dir(self.out_public_dir.unwrap_or_else(|| "public".into()))
+            .set_out_assets_dir(self.out_assets_dir.unwrap_or_else(|| "assets".into())))
+    }
+}
+
+/// Combines `lightningcss` flags by their names, like `Nesting` for `Features::Nesting`.
+fn flags_from_names<F>(names: Vec<String>, from_name: fn(&str) -> Option<F>) -> CremeResult<F>
+where
+    F: Default + std::ops::BitOr<Output = F>,
+{
+    names.into_iter().try_fold(F::default(), |flags, name| {
+        let flag = from_name(&name).ok_or(CremeError::InvalidCssFlag(name))?;
+        Ok(flags | flag)
+    })
+}
+
+impl Creme {
+    /// Creates a new Creme instance from a `creme.toml` or `creme.json` config file.
+    /// Anything not set in the file uses the recommended defaults.
+    ///
+    /// # Errors
+    ///
+    /// This will return an error if the config file can't be read or parsed,
+    /// or if the configured assets directory doesn't exist.
+    pub fn from_config_file(path: impl Into<PathBuf>) -> CremeResult<Self> {
+        CremeConfig::from_file(path)?.apply(Creme::new())
+    }
+}
@@ -1,85 +1,288 @@
-use std::path::{Path, PathBuf};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
 
+use base64::{prelude::BASE64_STANDARD, Engine};
 use lightningcss::{
-    bundler::{Bundler, FileProvider},
+    bundler::{Bundler, FileProvider, ResolveResult, SourceProvider},
     dependencies::DependencyOptions,
-    error::{Error as LightningCssError, PrinterErrorKind},
-    stylesheet::{ParserOptions, PrinterOptions, MinifyOptions},
+    error::{Error as LightningCssError, MinifyErrorKind, PrinterErrorKind},
+    stylesheet::{MinifyOptions, ParserOptions, PrinterOptions},
     targets::Targets,
 };
-use once_cell::sync::Lazy;
+use parcel_sourcemap::{SourceMap, SourceMapError};
 use path_absolutize::Absolutize;
 use thiserror::Error;
 
-use crate::MANIFEST;
+use crate::{paths, AssetSource, CssConfig, Manifest};
 
 #[derive(Error, Debug)]
 pub enum BundleError {
-    #[error("bundler error: {0}")]
-    Bundler(String),
-    #[error("print error: {0}")]
-    Print(#[from] LightningCssError<PrinterErrorKind>),
+    #[error("bundler error in {}: {message}", .path.display())]
+    Bundler { path: PathBuf, message: String },
+    #[error("minify error in {}: {source}", .path.display())]
+    Minify {
+        path: PathBuf,
+        source: LightningCssError<MinifyErrorKind>,
+    },
+    #[error("print error in {}: {source}", .path.display())]
+    Print {
+        path: PathBuf,
+        source: LightningCssError<PrinterErrorKind>,
+    },
+    #[error("source map error in {}: {source}", .path.display())]
+    SourceMap {
+        path: PathBuf,
+        source: SourceMapError,
+    },
+    #[error("{url} in {} isn't a bundled asset", .path.display())]
+    MissingAsset { path: PathBuf, url: String },
+}
+
+/// The output of processing a single stylesheet.
+pub(crate) struct ProcessedCss {
+    pub code: String,
+    /// The JSON source map, if source maps were requested.
+    pub source_map: Option<String>,
+    /// Every file bundled into the stylesheet, other than the stylesheet itself.
+    pub imports: Vec<PathBuf>,
+}
+
+/// A `FileProvider` that also resolves bare `@import` specifiers against a list
+/// of extra directories, like Sass load paths.
+struct ImportPathProvider<'a> {
+    files: FileProvider,
+    assets: &'a AssetSource,
+    import_paths: &'a [PathBuf],
+    /// Contents to use for a file instead of reading it, like generated CSS.
+    generated: Option<(&'a Path, &'a str)>,
+}
+
+impl<'a> ImportPathProvider<'a> {
+    fn new(
+        assets: &'a AssetSource,
+        import_paths: &'a [PathBuf],
+        generated: Option<(&'a Path, &'a str)>,
+    ) -> Self {
+        Self {
+            files: FileProvider::new(),
+            assets,
+            import_paths,
+            generated,
+        }
+    }
+}
+
+impl ImportPathProvider<'_> {
+    /// Finds the file at the same path as `path` in the asset directories,
+    /// preferring the overlay. `None` if `path` isn't in them, or doesn't exist.
+    fn overlaid(&self, path: &Path) -> Option<PathBuf> {
+        let src_path = self
+            .assets
+            .dirs()
+            .find_map(|dir| path.strip_prefix(dir).ok())?;
+
+        self.assets
+            .dirs()
+            .map(|dir| dir.join(src_path))
+            .find(|path| path.is_file())
+    }
 }
 
-static FILE_PROVIDER: Lazy<FileProvider> = Lazy::new(FileProvider::new);
+impl SourceProvider for ImportPathProvider<'_> {
+    type Error = std::io::Error;
 
-// TODO: omg this is so bad
-fn resolve_url(dep_url: &String, src_path: &Path, assets_dir: &PathBuf) -> String {
+    fn read<'a>(&'a self, file: &Path) -> Result<&'a str, Self::Error> {
+        match self.generated {
+            Some((path, code)) if path == file => Ok(code),
+            _ => self.files.read(file),
+        }
+    }
+
+    fn resolve(
+        &self,
+        specifier: &str,
+        originating_file: &Path,
+    ) -> Result<ResolveResult, Self::Error> {
+        // Relative imports always win, so existing stylesheets behave the same.
+        // Inside the assets, they resolve against the overlay first, like assets do.
+        let relative = originating_file.with_file_name(specifier);
+        if let Some(overlaid) = self.overlaid(&relative) {
+            return Ok(overlaid.into());
+        }
+
+        if relative.is_file() {
+            return Ok(relative.into());
+        }
+
+        Ok(self
+            .import_paths
+            .iter()
+            .map(|import_path| import_path.join(specifier))
+            .find(|path| path.is_file())
+            .unwrap_or(relative)
+            .into())
+    }
+}
+
+/// The URL a dependency is rewritten to. `None` if it points at a file that
+/// isn't in the manifest, like one outside the assets directory, or a stylesheet
+/// that hasn't been processed yet.
+fn resolve_url(dep_url: &str, src_path: &Path, assets: &AssetSource, manifest: &Manifest) -> Option<String> {
     if dep_url.starts_with("https://") || dep_url.starts_with("http://") {
-        return dep_url.clone();
+        return Some(dep_url.to_string());
     }
 
-    let full_asset_path = std::fs::canonicalize(assets_dir).unwrap();
+    let url = resolve_src_url(dep_url, src_path, assets)?;
+
+    let entry = manifest.assets.get(&url)?;
+
+    if entry.is_inline() {
+        Some(entry.url.clone())
+    } else {
+        Some(format!("/{}", entry.url))
+    }
+}
 
-    let full_path = full_asset_path
-        .join(src_path.strip_prefix(assets_dir).unwrap().parent().unwrap())
-        .join(dep_url);
-    let binding = full_path.absolutize().unwrap();
-    let url = binding.strip_prefix(full_asset_path).unwrap();
+/// The path of the asset a URL points at, relative to the assets directory,
+/// which is its key in the manifest. `None` if it's outside the asset directories.
+fn resolve_src_url(dep_url: &str, src_path: &Path, assets: &AssetSource) -> Option<String> {
+    // Both sides are canonicalized, so they agree on prefixes and separators.
+    let full_src_path = paths::canonicalize(src_path).ok()?;
 
-    let url = url.to_str().unwrap().replace('\\', "/");
+    let full_path = full_src_path.parent()?.join(dep_url);
+    let binding = full_path.absolutize().ok()?;
 
-    MANIFEST
-        .lock()
-        .unwrap()
-        .assets
-        .get(&url)
-        .cloned()
-        .unwrap()
+    // A stylesheet in the overlay can point at an asset in the assets directory,
+    // and the other way around, so the URL is relative to whichever one it's in.
+    assets
+        .dirs()
+        .find_map(|dir| {
+            let full_dir = paths::canonicalize(dir).ok()?;
+            binding.strip_prefix(full_dir).ok().map(paths::to_url)
+        })
 }
 
+/// A base64 data URI of the asset a `url()` points at, if it's smaller than
+/// `threshold` bytes. The source file is inlined, since it isn't processed.
+fn inline_url(
+    dep_url: &str,
+    src_path: &Path,
+    assets: &AssetSource,
+    manifest: &Manifest,
+    threshold: usize,
+) -> Option<String> {
+    if dep_url.contains("://") || dep_url.starts_with("data:") {
+        return None;
+    }
+
+    let src_url = resolve_src_url(dep_url, src_path, assets)?;
+    let entry = manifest.assets.get(&src_url).filter(|entry| entry.size < threshold)?;
+
+    let file = assets
+        .dirs()
+        .map(|dir| dir.join(&src_url))
+        .find(|file| file.is_file())?;
+    let content = fs::read(file).ok()?;
+
+    Some(format!("data:{};base64,{}", entry.mime, BASE64_STANDARD.encode(content)))
+}
+
+/// Bundles, minifies and rewrites a stylesheet. `generated` is used as the
+/// stylesheet's contents instead of reading it, with `path` still used to
+/// resolve its imports and URLs.
 pub(crate) fn process_css(
     path: &Path,
-    parser_options: ParserOptions,
+    generated: Option<&str>,
     targets: impl Into<Targets>,
-    assets_dir: &PathBuf,
-) -> String {
-    // let mut bundler = Bundler::new_with_at_rule_parser(&*FILE_PROVIDER, None, parser_options);
-    let mut bundler = Bundler::new(&*FILE_PROVIDER, None, parser_options);
-    let mut stylesheet = bundler.bundle(path).unwrap();
+    assets: &AssetSource,
+    config: &CssConfig,
+    manifest: &Manifest,
+) -> Result<ProcessedCss, BundleError> {
+    let provider = ImportPathProvider::new(
+        assets,
+        &config.import_paths,
+        generated.map(|code| (path, code)),
+    );
+    let parser_options = ParserOptions {
+        flags: config.parser_flags.clone(),
+        ..Default::default()
+    };
+    let mut source_map = config.source_maps.then(|| SourceMap::new("/"));
 
-    let targets = targets.into();
+    // The bundler borrows the source map to register each bundled file,
+    // so it needs to be dropped before the map is handed to the printer.
+    let mut stylesheet = {
+        // let mut bundler = Bundler::new_with_at_rule_parser(&provider, None, parser_options);
+        let mut bundler = Bundler::new(&provider, source_map.as_mut(), parser_options);
+        bundler
+            .bundle(path)
+            .map_err(|err| BundleError::Bundler {
+                path: path.to_path_buf(),
+                message: err.to_string(),
+            })?
+    };
 
-    stylesheet.minify(MinifyOptions {
-        targets,
-        ..Default::default()
-    }).unwrap();
+    let imports = stylesheet
+        .sources
+        .iter()
+        .map(PathBuf::from)
+        .filter(|source| source != path)
+        .collect();
+
+    let targets = Targets {
+        include: config.include_features,
+        exclude: config.exclude_features,
+        ..targets.into()
+    };
+
+    stylesheet
+        .minify(MinifyOptions {
+            targets,
+            ..Default::default()
+        })
+        .map_err(|source| BundleError::Minify {
+            path: path.to_path_buf(),
+            source,
+        })?;
 
     let css = stylesheet
         .to_css(PrinterOptions {
-            minify: true,
+            minify: config.minify,
+            source_map: source_map.as_mut(),
             targets,
             analyze_dependencies: Some(DependencyOptions {
                 remove_imports: false,
             }),
             ..PrinterOptions::default()
         })
-        .unwrap();
+        .map_err(|source| BundleError::Print {
+            path: path.to_path_buf(),
+            source,
+        })?;
+
+    let source_map = source_map
+        .map(|mut source_map| source_map.to_json(None))
+        .transpose()
+        .map_err(|source| BundleError::SourceMap {
+            path: path.to_path_buf(),
+            source,
+        })?;
 
     let mut code = css.code;
 
-    css.dependencies.unwrap().iter().for_each(|dep| {
-        let (placeholder, path, url) = match dep {
+    // Stylesheets without any `url()` or `@import` have nothing to rewrite.
+    let Some(dependencies) = css.dependencies else {
+        return Ok(ProcessedCss {
+            code,
+            source_map,
+            imports,
+        });
+    };
+
+    for dep in &dependencies {
+        let (placeholder, dep_path, url) = match dep {
             lightningcss::dependencies::Dependency::Url(url_dep) => {
                 (&url_dep.placeholder, &url_dep.loc.file_path, &url_dep.url)
             }
@@ -88,11 +291,34 @@ pub(crate) fn process_css(
             }
         };
 
-        let resolved_path = resolve_url(url, &PathBuf::from(path), assets_dir);
+        let src_path = PathBuf::from(dep_path);
 
-        // TODO: Probably need to include the / in the manifest
-        code = code.replace(placeholder, &format!("/{resolved_path}"));
-    });
+        // Imports are bundled in, so only `url()`s are inlined.
+        let inlined = match (dep, config.inline_threshold) {
+            (lightningcss::dependencies::Dependency::Url(_), Some(threshold)) => {
+                inline_url(url, &src_path, assets, manifest, threshold)
+            }
+            _ => None,
+        };
+
+        if let Some(data_uri) = inlined {
+            code = code.replace(placeholder, &data_uri);
+            continue;
+        }
+
+        let resolved_url = resolve_url(url, &src_path, assets, manifest).ok_or_else(|| {
+            BundleError::MissingAsset {
+                path: src_path.clone(),
+                url: url.clone(),
+            }
+        })?;
+
+        code = code.replace(placeholder, &resolved_url);
+    }
 
-    code
+    Ok(ProcessedCss {
+        code,
+        source_map,
+        imports,
+    })
 }
@@ -0,0 +1,132 @@
+use std::{
+    collections::{HashMap, HashSet},
+    fmt::Write as _,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    cache_control::CacheControlRules, compress::Encoding, paths, AssetSourceConfig, CremeResult,
+};
+
+/// Writes a Rust expression that embeds every file in `dist_dir` into the
+/// binary, as a `creme::embed::EmbeddedAssets`. This is `include!`d by the
+/// `creme::service!()` macro in release mode.
+///
+/// Files under `immutable_dir` are content-hashed, so they get served with
+/// a far-future cache lifetime, unless a `cache_control` rule matches them.
+/// Files in `downloads`, by URL, are served as attachments under their original name.
+///
+/// Sidecar files for `encodings` (see `compress::write_precompressed`) are
+/// embedded as variants of the file they belong to, not as assets of their own.
+pub(crate) fn write_embedded_assets(
+    dist_dir: &Path,
+    immutable_dir: Option<&Path>,
+    source_config: &AssetSourceConfig,
+    cache_control: &CacheControlRules,
+    downloads: &HashMap<String, String>,
+    encodings: &[Encoding],
+    out_file: &Path,
+) -> CremeResult<()> {
+    let mut files = Vec::new();
+    collect_files(dist_dir, &mut files)?;
+    files.sort();
+
+    let sidecars: HashSet<PathBuf> = files
+        .iter()
+        .flat_map(|file| encodings.iter().map(|encoding| encoding.sidecar_path(file)))
+        .filter(|sidecar| sidecar.is_file())
+        .collect();
+
+    let mut code = String::from("{\n    static ASSETS: &[::creme::embed::EmbeddedAsset] = &[\n");
+
+    for file in files {
+        if sidecars.contains(&file) {
+            continue;
+        }
+
+        let content = fs::read(&file)?;
+
+        let url = paths::to_url(file.strip_prefix(dist_dir).unwrap());
+        let mime = source_config.guess_mime(&file);
+        let hash = blake3::hash(&content).to_hex();
+        let etag = format!("\"{hash}\"");
+        let immutable = immutable_dir.is_some_and(|dir| file.starts_with(dir));
+        let cache_control = cache_control.get(&url);
+        let content_disposition = downloads.get(&url).map(|name| attachment(name));
+
+        // Each encoding is a different representation, so it gets its own ETag.
+        let mut variants = String::new();
+        for encoding in encodings {
+            let sidecar = encoding.sidecar_path(&file);
+            if sidecars.contains(&sidecar) {
+                write!(
+                    variants,
+                    "::creme::embed::EncodedVariant {{ encoding: {encoding:?}, etag: {etag:?}, content: ::core::include_bytes!({sidecar:?}) }}, ",
+                    encoding = encoding.content_encoding(),
+                    etag = format!("\"{hash}-{}\"", encoding.extension()),
+                    sidecar = sidecar.to_str().unwrap(),
+                )
+                .unwrap();
+            }
+        }
+
+        // Debug formatting produces valid, escaped Rust string literals.
+        writeln!(
+            code,
+            "        ::creme::embed::EmbeddedAsset {{ path: {url:?}, mime: {mime:?}, etag: {etag:?}, immutable: {immutable}, cache_control: {cache_control:?}, content_disposition: {content_disposition:?}, content: ::core::include_bytes!({file:?}), encodings: &[{variants}] }},",
+            mime = mime.as_ref(),
+            file = file.to_str().unwrap(),
+        )
+        .unwrap();
+    }
+
+    code.push_str("    ];\n    ::creme::embed::EmbeddedAssets::new(ASSETS)\n}\n");
+
+    fs::write(out_file, code)?;
+
+    Ok(())
+}
+
+pub(crate) fn collect_files(dir: &Path, files: &mut Vec<PathBuf>) -> CremeResult<()> {
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+
+        if path.is_dir() {
+            collect_files(&path, files)?;
+        } else {
+            files.push(path);
+        }
+    }
+
+    Ok(())
+}
+
+/// A `Content-Disposition` value that downloads a file as `name`. Names that
+/// aren't plain ASCII also get an RFC 5987 `filename*`, with a fallback for
+/// clients that don't support it.
+fn attachment(name: &str) -> String {
+    let fallback: String = name
+        .chars()
+        .map(|c| match c {
+            '"' | '\\' => '_',
+            ' '..='~' => c,
+            _ => '_',
+        })
+        .collect();
+
+    if fallback == name {
+        return format!("attachment; filename=\"{name}\"");
+    }
+
+    let mut encoded = String::new();
+    for byte in name.bytes() {
+        if byte.is_ascii_alphanumeric() || b"!#$&+-.^_`|~".contains(&byte) {
+            encoded.push(byte as char);
+        } else {
+            write!(encoded, "%{byte:02X}").unwrap();
+        }
+    }
+
+    format!("attachment; filename=\"{fallback}\"; filename*=UTF-8''{encoded}")
+}
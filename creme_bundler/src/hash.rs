@@ -0,0 +1,58 @@
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+
+/// The algorithm used to hash output filenames in release mode.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum HashAlgorithm {
+    #[default]
+    Blake3,
+    Sha256,
+    /// The 64-bit XXH3 hash, which is fast but not cryptographic.
+    Xxh3,
+}
+
+impl HashAlgorithm {
+    /// Hashes `content`, returning the full digest.
+    /// XXH3's digest is big-endian, like its usual hex representation.
+    pub fn digest(self, content: &[u8]) -> Vec<u8> {
+        match self {
+            HashAlgorithm::Blake3 => blake3::hash(content).as_bytes().to_vec(),
+            HashAlgorithm::Sha256 => Sha256::digest(content).to_vec(),
+            HashAlgorithm::Xxh3 => xxhash_rust::xxh3::xxh3_64(content).to_be_bytes().to_vec(),
+        }
+    }
+}
+
+/// What output paths are hashed from, in release mode.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HashSource {
+    /// The processed output, so a hash always matches what's served. Upgrading
+    /// lightningcss or changing the browser targets can change the output of
+    /// every stylesheet, and with it their hashes, even if no source changed.
+    #[default]
+    Processed,
+    /// The source files, along with the files bundled into them and the URLs
+    /// of the assets they point at, so hashes only change with the sources and
+    /// caches stay warm across toolchain upgrades. In exchange, an upgrade can
+    /// change what's served under a name browsers have already cached.
+    Original,
+}
+
+/// Where the hash goes in output paths, in release mode.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HashStrategy {
+    /// A short hash is added to the filename, like `assets/cat-1a2b3c4d.jpeg`.
+    #[default]
+    Filename,
+    /// The file keeps its name, in a directory named after its full hash,
+    /// like `assets/1a2b.../cat.jpeg`.
+    DirectoryHash,
+    /// The file keeps its name, and its URL gets a short hash as a query string,
+    /// like `assets/cat.jpeg?v=1a2b3c4d`, for CDNs and proxies that don't get
+    /// along with hashed filenames. Since the file is overwritten by each
+    /// release, it isn't served as immutable.
+    Query,
+}
@@ -0,0 +1,185 @@
+use std::{cell::Cell, fs, path::Path, rc::Rc};
+
+use lol_html::{
+    doc_comments, doc_text, element, errors::RewritingError, html_content::ContentType,
+    rewrite_str, RewriteStrSettings,
+};
+
+use crate::{embed, AssetSourceConfig, CremeError, CremeResult, Manifest};
+
+/// Attributes that hold a single URL.
+const URL_ATTRIBUTES: [&str; 3] = ["src", "href", "poster"];
+
+/// Elements whose whitespace is significant, or whose content isn't HTML.
+const RAW_ELEMENTS: &str = "pre, textarea, script, style";
+
+/// What to do to public HTML files.
+pub(crate) struct HtmlOptions<'a> {
+    /// Rewrite asset references to their output URLs, using this manifest.
+    pub rewrite: Option<&'a Manifest>,
+    /// The URL assets are served under in development, like `assets`.
+    pub dev_url: &'a str,
+    /// Remove comments and collapse whitespace.
+    pub minify: bool,
+}
+
+/// Processes every HTML file in `dir` in place.
+///
+/// Asset references use the development URLs, like `/assets/img/cat.jpeg`,
+/// so the same HTML works in both modes. See `AssetSource::dev_url`.
+pub(crate) fn process_html_files(
+    dir: &Path,
+    source_config: &AssetSourceConfig,
+    options: &HtmlOptions,
+) -> CremeResult<()> {
+    let mut files = Vec::new();
+    embed::collect_files(dir, &mut files)?;
+
+    for file in files {
+        if source_config.guess_mime(&file) != mime::TEXT_HTML {
+            continue;
+        }
+
+        let html = fs::read_to_string(&file)?;
+        let processed = process_html(&html, options).map_err(|source| CremeError::Html {
+            path: file.clone(),
+            source,
+        })?;
+
+        fs::write(&file, processed)?;
+    }
+
+    Ok(())
+}
+
+fn process_html(html: &str, options: &HtmlOptions) -> Result<String, RewritingError> {
+    let mut element_content_handlers = Vec::new();
+    let mut document_content_handlers = Vec::new();
+
+    if let Some(manifest) = options.rewrite {
+        let dev_url = options.dev_url;
+
+        element_content_handlers.push(element!("[src], [href], [poster], [srcset]", move |el| {
+            for attribute in URL_ATTRIBUTES {
+                if let Some(url) = el.get_attribute(attribute) {
+                    if let Some(rewritten) = rewrite_url(&url, manifest, dev_url) {
+                        el.set_attribute(attribute, &rewritten)?;
+                    }
+                }
+            }
+
+            if let Some(srcset) = el.get_attribute("srcset") {
+                el.set_attribute("srcset", &rewrite_srcset(&srcset, manifest, dev_url))?;
+            }
+
+            Ok(())
+        }));
+    }
+
+    if options.minify {
+        // How many raw elements the parser is inside of.
+        let raw_depth = Rc::new(Cell::new(0usize));
+
+        let depth = raw_depth.clone();
+        element_content_handlers.push(element!(RAW_ELEMENTS, move |el| {
+            if let Some(handlers) = el.end_tag_handlers() {
+                depth.set(depth.get() + 1);
+
+                let depth = depth.clone();
+                handlers.push(Box::new(move |_| {
+                    depth.set(depth.get() - 1);
+                    Ok(())
+                }));
+            }
+
+            Ok(())
+        }));
+
+        document_content_handlers.push(doc_text!(move |text| {
+            if raw_depth.get() == 0 {
+                let collapsed = collapse_whitespace(text.as_str());
+                text.replace(&collapsed, ContentType::Html);
+            }
+
+            Ok(())
+        }));
+
+        // Conditional comments are kept, since old browsers act on them.
+        document_content_handlers.push(doc_comments!(|comment| {
+            if !comment.text().starts_with("[if") {
+                comment.remove();
+            }
+
+            Ok(())
+        }));
+    }
+
+    rewrite_str(
+        html,
+        RewriteStrSettings {
+            element_content_handlers,
+            document_content_handlers,
+            ..RewriteStrSettings::default()
+        },
+    )
+}
+
+/// Collapses each run of whitespace into a single space. Runs aren't removed
+/// entirely, since whitespace between inline elements is rendered.
+fn collapse_whitespace(text: &str) -> String {
+    let mut collapsed = String::with_capacity(text.len());
+    let mut in_whitespace = false;
+
+    for c in text.chars() {
+        if c.is_ascii_whitespace() {
+            if !in_whitespace {
+                collapsed.push(' ');
+            }
+            in_whitespace = true;
+        } else {
+            collapsed.push(c);
+            in_whitespace = false;
+        }
+    }
+
+    collapsed
+}
+
+/// Rewrites each URL in a `srcset`, like `a.png 1x, b.png 2x`.
+fn rewrite_srcset(srcset: &str, manifest: &Manifest, dev_url: &str) -> String {
+    srcset
+        .split(',')
+        .map(|candidate| {
+            let candidate = candidate.trim();
+            let (url, descriptor) = candidate.split_once(' ').unwrap_or((candidate, ""));
+
+            match rewrite_url(url, manifest, dev_url) {
+                Some(url) if descriptor.is_empty() => url,
+                Some(url) => format!("{url} {descriptor}"),
+                None => candidate.to_string(),
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Maps a development asset URL to its output URL, keeping any query or fragment.
+/// Returns `None` for anything that isn't a known asset.
+fn rewrite_url(url: &str, manifest: &Manifest, dev_url: &str) -> Option<String> {
+    let end = url.find(['?', '#']).unwrap_or(url.len());
+    let (path, suffix) = url.split_at(end);
+
+    let (root, path) = match path.strip_prefix('/') {
+        Some(path) => ("/", path),
+        None => ("", path),
+    };
+
+    let src_url = path.strip_prefix(dev_url)?.strip_prefix('/')?;
+    let entry = manifest.assets.get(src_url)?;
+
+    if entry.is_inline() {
+        return Some(entry.url.clone());
+    }
+
+    Some(format!("{root}{}{suffix}", entry.url))
+}
@@ -0,0 +1,54 @@
+use std::{
+    collections::HashSet,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use include_dir::Dir;
+
+use crate::{embed::collect_files, is_unchanged, CremeResult};
+
+/// Writes the files of an `include_dir!` directory to `extract_dir`, since
+/// assets are processed from disk.
+///
+/// Unchanged files aren't rewritten, and files that are no longer included are
+/// removed, so the build script isn't rerun because of its own writes.
+pub(crate) fn extract(dir: &Dir<'_>, extract_dir: &Path) -> CremeResult<()> {
+    let mut written = HashSet::new();
+    write_files(dir, extract_dir, &mut written)?;
+
+    let mut files = Vec::new();
+    collect_files(extract_dir, &mut files)?;
+
+    for file in files {
+        if !written.contains(&file) {
+            fs::remove_file(file)?;
+        }
+    }
+
+    Ok(())
+}
+
+fn write_files(
+    dir: &Dir<'_>,
+    extract_dir: &Path,
+    written: &mut HashSet<PathBuf>,
+) -> CremeResult<()> {
+    fs::create_dir_all(extract_dir.join(dir.path()))?;
+
+    for file in dir.files() {
+        let path = extract_dir.join(file.path());
+
+        if !is_unchanged(&path, file.contents()) {
+            fs::write(&path, file.contents())?;
+        }
+
+        written.insert(path);
+    }
+
+    for dir in dir.dirs() {
+        write_files(dir, extract_dir, written)?;
+    }
+
+    Ok(())
+}
@@ -0,0 +1,88 @@
+use std::path::Path;
+
+use path_absolutize::Absolutize;
+
+use crate::{paths, AssetSource, Manifest};
+
+/// Quotes that can start and end a string in JavaScript.
+const QUOTES: [char; 3] = ['\'', '"', '`'];
+
+/// Rewrites references to `.wasm` files in JavaScript, like the
+/// `new URL('app_bg.wasm', import.meta.url)` in `wasm-bindgen` glue,
+/// to the file's output URL, relative to `out_dir_url`.
+///
+/// Only string literals that end in `.wasm` and point at a bundled asset
+/// are changed.
+pub(crate) fn rewrite_wasm_urls(
+    code: &str,
+    js_path: &Path,
+    assets: &AssetSource,
+    out_dir_url: &str,
+    manifest: &Manifest,
+) -> String {
+    let mut rewritten = String::with_capacity(code.len());
+    let mut rest = code;
+
+    while let Some((start, end)) = next_wasm_literal(rest) {
+        let literal = &rest[start..end];
+        rewritten.push_str(&rest[..start]);
+
+        match resolve_wasm_url(literal, js_path, assets, out_dir_url, manifest) {
+            Some(url) => rewritten.push_str(&url),
+            None => rewritten.push_str(literal),
+        }
+
+        rest = &rest[end..];
+    }
+
+    rewritten.push_str(rest);
+    rewritten
+}
+
+/// Finds the contents of the next string literal ending in `.wasm`,
+/// returning its start and end, without the quotes.
+fn next_wasm_literal(code: &str) -> Option<(usize, usize)> {
+    let mut offset = 0;
+
+    loop {
+        let found = code[offset..].find(".wasm")? + offset;
+        let end = found + ".wasm".len();
+        offset = end;
+
+        let Some(quote) = code[end..].chars().next().filter(|c| QUOTES.contains(c)) else {
+            continue;
+        };
+
+        // The literal can't span lines, so only look back to the start of the line.
+        let line_start = code[..found].rfind('\n').map_or(0, |i| i + 1);
+        if let Some(start) = code[line_start..found].rfind(quote) {
+            return Some((line_start + start + 1, end));
+        }
+    }
+}
+
+fn resolve_wasm_url(
+    literal: &str,
+    js_path: &Path,
+    assets: &AssetSource,
+    out_dir_url: &str,
+    manifest: &Manifest,
+) -> Option<String> {
+    if literal.contains("://") {
+        return None;
+    }
+
+    let wasm_path = js_path.parent()?.join(literal);
+    let wasm_path = wasm_path.absolutize().ok()?;
+    let src_url = assets.dirs().find_map(|dir| {
+        let dir = dir.absolutize().ok()?;
+        wasm_path.strip_prefix(dir).ok().map(paths::to_url)
+    })?;
+
+    let entry = manifest.assets.get(&src_url)?;
+    if entry.is_inline() {
+        return Some(entry.url.clone());
+    }
+
+    Some(paths::relative_url(out_dir_url, &entry.url))
+}
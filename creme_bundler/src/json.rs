@@ -0,0 +1,43 @@
+use std::path::Path;
+
+use serde::de::IgnoredAny;
+
+use crate::{CremeError, CremeResult};
+
+/// Checks that a JSON asset parses, optionally minifying it.
+///
+/// Minifying only removes whitespace between tokens, so keys keep their order
+/// and numbers keep their exact text.
+pub(crate) fn process_json(path: &Path, content: Vec<u8>, minify: bool) -> CremeResult<Vec<u8>> {
+    serde_json::from_slice::<IgnoredAny>(&content).map_err(|source| CremeError::Json {
+        path: path.to_path_buf(),
+        source,
+    })?;
+
+    if !minify {
+        return Ok(content);
+    }
+
+    let mut minified = Vec::with_capacity(content.len());
+    let mut in_string = false;
+    let mut escaped = false;
+
+    for byte in content {
+        if in_string {
+            match byte {
+                _ if escaped => escaped = false,
+                b'\\' => escaped = true,
+                b'"' => in_string = false,
+                _ => {}
+            }
+        } else if byte.is_ascii_whitespace() {
+            continue;
+        } else if byte == b'"' {
+            in_string = true;
+        }
+
+        minified.push(byte);
+    }
+
+    Ok(minified)
+}
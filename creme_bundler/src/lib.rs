@@ -1,40 +1,282 @@
+use base64::{prelude::BASE64_STANDARD, Engine};
+use globset::{Glob, GlobBuilder, GlobSet, GlobSetBuilder};
 use hex::ToHex;
-use lightningcss::{
-    stylesheet::{ParserFlags, ParserOptions},
-    targets::Browsers,
-};
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+use lightningcss::targets::Browsers;
 use mime::Mime;
-use once_cell::sync::Lazy;
 use path_absolutize::Absolutize;
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 use std::{
-    collections::HashMap,
+    borrow::Cow,
+    collections::{BTreeMap, HashMap, HashSet},
     ffi::{OsStr, OsString},
+    fmt,
     fs::{self, File},
     io::{self, BufWriter},
     path::{Path, PathBuf},
-    sync::Mutex,
+    sync::Arc,
+    time::{Duration, Instant},
 };
 use thiserror::Error;
 
+#[cfg(feature = "tokio")]
+mod bundle_async;
+mod cache_control;
+mod compress;
+mod config;
 mod css;
+mod embed;
+mod hash;
+mod html;
+#[cfg(feature = "include_dir")]
+mod included;
+mod js;
+mod json;
+mod paths;
+mod processor;
+mod references;
+mod rust_module;
+#[cfg(feature = "sniff")]
+mod sniff;
+#[cfg(feature = "tailwind")]
+mod tailwind;
+#[cfg(feature = "watch")]
+mod watch;
+
+pub use config::{CacheControlRule, ConfigReleaseMode, CremeConfig};
+pub use hash::{HashAlgorithm, HashSource, HashStrategy};
+pub use lightningcss::{stylesheet::ParserFlags, targets::Features};
+pub use processor::{AssetProcessor, ProcessContext};
+#[cfg(feature = "tailwind")]
+pub use tailwind::TailwindConfig;
+use cache_control::CacheControlRules;
+use compress::Encoding;
+use paths::DirWalk;
 
 const MANIFEST_FILE: &str = "creme-manifest.json";
+const EMBED_FILE: &str = "creme-embed.rs";
+
+/// Where assets from `Creme::set_assets_include_dir` are written, in the out directory.
+#[cfg(feature = "include_dir")]
+const INCLUDE_DIR: &str = "creme-include";
+
+/// Gitignore-style patterns for files in the assets directory that aren't assets.
+const CREME_IGNORE_FILE: &str = ".cremeignore";
+const GIT_IGNORE_FILE: &str = ".gitignore";
+
+/// The URL assets are served under in development mode, if the assets
+/// directory has no name, like `/`.
+const DEV_ASSETS_URL: &str = "assets";
 
-#[derive(Debug, Serialize)]
+/// Bumped whenever the manifest format changes, so `creme_macros` can reject
+/// manifests written by an older bundler instead of misreading them.
+const MANIFEST_VERSION: u32 = 2;
+
+#[derive(Debug, Serialize, Deserialize)]
 struct Manifest {
-    assets: HashMap<String, String>,
+    version: u32,
+
+    /// Sorted, so identical inputs produce an identical manifest.
+    assets: BTreeMap<String, ManifestEntry>,
+
+    /// Output entries by the hash of their content and their mime type, used
+    /// to share a single output file between identical assets. The same bytes
+    /// under another type, like an empty `.js` and `.css` file, aren't shared,
+    /// since the entry's type would be wrong for one of them.
+    #[serde(skip)]
+    outputs: HashMap<(blake3::Hash, Mime), ManifestEntry>,
+
+    /// The source each output URL was written from, to catch two sources
+    /// being written to the same file, like after flattening.
+    #[serde(skip)]
+    sources: HashMap<String, PathBuf>,
+
+    /// The assets in each asset group, by their path in the assets directory.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    groups: BTreeMap<String, Vec<String>>,
+}
+
+impl Manifest {
+    /// Records that `source` is written to `url`.
+    ///
+    /// # Errors
+    ///
+    /// This will return an error if a different source is already written there.
+    fn claim_output(&mut self, url: &str, source: &Path) -> CremeResult<()> {
+        match self.sources.get(url) {
+            Some(existing) if existing != source => Err(CremeError::OutputCollision {
+                url: url.to_string(),
+                first: existing.clone(),
+                second: source.to_path_buf(),
+            }),
+            Some(_) => Ok(()),
+            None => {
+                self.sources.insert(url.to_string(), source.to_path_buf());
+                Ok(())
+            }
+        }
+    }
+
+    /// Records the assets matched by each group's globs.
+    fn add_groups(&mut self, groups: &[(String, GlobSet)]) {
+        for (name, globs) in groups {
+            let members = self
+                .assets
+                .keys()
+                .filter(|src_url| globs.is_match(src_url))
+                .cloned()
+                .collect();
+
+            self.groups.insert(name.clone(), members);
+        }
+    }
+
+    /// Records the `Cache-Control` value each asset is served with, if a rule matches it.
+    fn add_cache_control(&mut self, rules: &CacheControlRules) {
+        for entry in self.assets.values_mut().filter(|entry| !entry.is_inline()) {
+            entry.cache_control = rules.get(entry.path()).map(str::to_string);
+        }
+    }
+
+    /// Marks the assets matched by the download globs to be downloaded
+    /// under their original file name, like `guide.pdf`.
+    fn add_downloads(&mut self, globs: &GlobSet) {
+        for (src_url, entry) in &mut self.assets {
+            if globs.is_match(src_url) {
+                let name = src_url.rsplit('/').next().unwrap_or(src_url);
+                entry.download = Some(name.to_string());
+            }
+        }
+    }
+
+    /// Lets a hook add, change and remove asset URLs. Added assets get a mime
+    /// type guessed from their path, and no size or hash, since there's no
+    /// content to go by.
+    fn apply_hook(&mut self, hook: &ManifestHook) {
+        let mut urls = self
+            .assets
+            .iter()
+            .map(|(src_url, entry)| (src_url.clone(), entry.url.clone()))
+            .collect();
+
+        (hook.0)(&mut urls);
+
+        self.assets.retain(|src_url, _| urls.contains_key(src_url));
+
+        for (src_url, url) in urls {
+            match self.assets.get_mut(&src_url) {
+                Some(entry) => entry.url = url,
+                None => {
+                    let mime = mime_guess::from_path(&src_url).first_or_octet_stream();
+                    let entry = ManifestEntry {
+                        hash: String::new(),
+                        ..ManifestEntry::new(url, &mime, &[])
+                    };
+                    self.assets.insert(src_url, entry);
+                }
+            }
+        }
+    }
+
+    /// The original file names of downloadable assets, by the URL they're served at.
+    fn downloads(&self) -> HashMap<String, String> {
+        self.assets
+            .values()
+            .filter_map(|entry| Some((entry.path().to_string(), entry.download.clone()?)))
+            .collect()
+    }
+
+    fn read(path: &Path) -> CremeResult<Self> {
+        let file = File::open(path)?;
+        Ok(serde_json::from_reader(io::BufReader::new(file))?)
+    }
+
+    fn write(&self, path: &Path) -> CremeResult<()> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        let file = File::create(path)?;
+        let writer = BufWriter::new(file);
+        serde_json::to_writer_pretty(writer, self)?;
+
+        Ok(())
+    }
+}
+
+impl Default for Manifest {
+    fn default() -> Self {
+        Self {
+            version: MANIFEST_VERSION,
+            assets: BTreeMap::new(),
+            outputs: HashMap::new(),
+            sources: HashMap::new(),
+            groups: BTreeMap::new(),
+        }
+    }
+}
+
+/// A function that edits asset URLs, by their path in the assets directory.
+type ManifestHookFn = dyn Fn(&mut HashMap<String, String>) + Send + Sync;
+
+/// Edits the asset URLs before the manifest is written. See `Creme::manifest_hook`.
+#[derive(Clone)]
+struct ManifestHook(Arc<ManifestHookFn>);
+
+impl fmt::Debug for ManifestHook {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("ManifestHook")
+    }
+}
+
+/// A bundled asset, as recorded in the manifest.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct ManifestEntry {
+    /// The URL of the output file, relative to the public directory.
+    url: String,
+    mime: String,
+    /// The size of the output file in bytes.
+    size: usize,
+    /// The hex-encoded blake3 hash of the output file.
+    hash: String,
+    /// The `Cache-Control` value it's served with, if a rule set one.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    cache_control: Option<String>,
+    /// The original file name it's downloaded as, if a download glob matched it.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    download: Option<String>,
 }
 
-static MANIFEST: Lazy<Mutex<Manifest>> = Lazy::new(|| {
-    Mutex::new(Manifest {
-        assets: HashMap::new(),
-    })
-});
+impl ManifestEntry {
+    fn new(url: String, mime: &Mime, content: &[u8]) -> Self {
+        Self {
+            url,
+            mime: mime.to_string(),
+            size: content.len(),
+            hash: blake3::hash(content).to_hex().to_string(),
+            cache_control: None,
+            download: None,
+        }
+    }
+
+    /// The path of the output file, relative to the public directory.
+    /// This is the URL without any cache-busting query string.
+    fn path(&self) -> &str {
+        self.url.split_once('?').map_or(&self.url, |(path, _)| path)
+    }
+
+    /// Whether the asset is inlined as a data URI, rather than written to a file.
+    fn is_inline(&self) -> bool {
+        self.url.starts_with("data:")
+    }
+}
 
-#[derive(Debug, PartialEq, Eq)]
-enum AssetType {
+/// The kind of an asset, from its mime type, which decides how it's processed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AssetType {
     Css,
+    /// JSON, including types like `application/manifest+json`.
+    Json(Mime),
     Other(Mime),
 }
 
@@ -45,80 +287,262 @@ impl From<Mime> for AssetType {
                 mime::CSS => AssetType::Css,
                 _ => AssetType::Other(mime),
             },
+            mime::APPLICATION
+                if mime.subtype() == mime::JSON || mime.suffix() == Some(mime::JSON) =>
+            {
+                AssetType::Json(mime)
+            }
             _ => AssetType::Other(mime),
         }
     }
 }
 
+impl AssetType {
+    /// Whether this is a JavaScript asset.
+    pub fn is_javascript(&self) -> bool {
+        matches!(self, AssetType::Other(mime) if mime.subtype() == mime::JAVASCRIPT)
+    }
+}
+
 impl From<AssetType> for Mime {
     fn from(asset_type: AssetType) -> Self {
         match asset_type {
             AssetType::Css => mime::TEXT_CSS,
-            AssetType::Other(mime) => mime,
+            AssetType::Json(mime) | AssetType::Other(mime) => mime,
         }
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 struct Asset {
     pub path: PathBuf,
     pub asset_type: AssetType,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 struct AssetSourceConfig {
     pub ignore_leading: Option<String>,
+
+    /// Mime types to use instead of guessing, by lowercase file extension.
+    pub mime_overrides: HashMap<String, Mime>,
+
+    /// Whether symlinks are followed, rather than skipped.
+    pub follow_symlinks: bool,
+
+    /// Whether the `.gitignore` in the assets directory is respected,
+    /// along with the `.cremeignore`.
+    pub respect_gitignore: bool,
+
+    /// Whether files whose extension gives no mime type, or that have none,
+    /// get it from their contents.
+    #[cfg(feature = "sniff")]
+    pub sniff_mime: bool,
 }
 
 impl Default for AssetSourceConfig {
     fn default() -> Self {
         Self {
             ignore_leading: Some("_".to_string()),
+            mime_overrides: HashMap::new(),
+            follow_symlinks: false,
+            respect_gitignore: false,
+            #[cfg(feature = "sniff")]
+            sniff_mime: false,
         }
     }
 }
 
-#[derive(Debug)]
+impl AssetSourceConfig {
+    /// Guesses the mime type of a file from its extension,
+    /// preferring any configured override. Files without an extension,
+    /// or with one that gives no mime type, are sniffed, if enabled.
+    fn guess_mime(&self, path: &Path) -> Mime {
+        let overridden = path.extension().and_then(|ext| {
+            self.mime_overrides
+                .get(&ext.to_string_lossy().to_lowercase())
+                .cloned()
+        });
+        if let Some(mime) = overridden {
+            return mime;
+        }
+
+        let mime = mime_guess::from_path(path).first_or_octet_stream();
+        #[cfg(feature = "sniff")]
+        if self.sniff_mime && mime == mime::APPLICATION_OCTET_STREAM {
+            return sniff::sniff_mime(path).unwrap_or(mime);
+        }
+
+        mime
+    }
+}
+
+#[derive(Debug, Clone)]
 struct AssetSource {
     pub src_dir: PathBuf,
+    /// A directory whose files take the place of the ones at the same path
+    /// in `src_dir`, like `assets.staging`.
+    pub overlay_dir: Option<PathBuf>,
     pub sources: Vec<Asset>,
     pub css_sources: Vec<Asset>,
-    pub _source_config: AssetSourceConfig,
+    pub source_config: AssetSourceConfig,
 }
 
 impl AssetSource {
     pub fn from_asset_dir(src_dir: impl Into<PathBuf>) -> io::Result<Self> {
+        Self::with_config(src_dir, AssetSourceConfig::default())
+    }
+
+    pub fn with_config(
+        src_dir: impl Into<PathBuf>,
+        source_config: AssetSourceConfig,
+    ) -> io::Result<Self> {
         let src_dir = src_dir.into();
 
         let mut sources = Vec::new();
         let mut css_sources = Vec::new();
-        let source_config = AssetSourceConfig::default();
 
         Self::add_assets(
             &mut sources,
             &mut css_sources,
-            &source_config.ignore_leading,
+            &source_config,
+            &Self::ignore_matcher(&src_dir, &source_config)?,
+            &mut DirWalk::new(source_config.follow_symlinks),
             &src_dir,
         )?;
 
-        Ok(Self {
+        let mut source = Self {
             src_dir,
+            overlay_dir: None,
             sources,
             css_sources,
-            _source_config: source_config,
-        })
+            source_config,
+        };
+        source.sort();
+
+        Ok(source)
+    }
+
+    /// Adds the assets in `overlay_dir`, replacing the ones at the same path
+    /// in the assets directory.
+    pub fn with_overlay(self, overlay_dir: impl Into<PathBuf>) -> io::Result<Self> {
+        let overlay_dir = overlay_dir.into();
+
+        let mut overlay_sources = Vec::new();
+        let mut overlay_css_sources = Vec::new();
+
+        Self::add_assets(
+            &mut overlay_sources,
+            &mut overlay_css_sources,
+            &self.source_config,
+            &Self::ignore_matcher(&overlay_dir, &self.source_config)?,
+            &mut DirWalk::new(self.source_config.follow_symlinks),
+            &overlay_dir,
+        )?;
+
+        let overridden: HashSet<PathBuf> = overlay_sources
+            .iter()
+            .chain(&overlay_css_sources)
+            .map(|asset| asset.path.strip_prefix(&overlay_dir).unwrap().to_path_buf())
+            .collect();
+        let is_kept = |asset: &Asset| {
+            !overridden.contains(asset.path.strip_prefix(&self.src_dir).unwrap())
+        };
+
+        let mut source = Self {
+            sources: self.sources.iter().filter(|a| is_kept(a)).cloned().collect(),
+            css_sources: self.css_sources.iter().filter(|a| is_kept(a)).cloned().collect(),
+            overlay_dir: Some(overlay_dir),
+            ..self
+        };
+        source.sources.extend(overlay_sources);
+        source.css_sources.extend(overlay_css_sources);
+        source.sort();
+
+        Ok(source)
+    }
+
+    fn sort(&mut self) {
+        // Directory order varies between filesystems. Sorting keeps the output
+        // the same between builds, like which of two identical assets is written.
+        // Assets are sorted by their path in the merged directory, so an overlay
+        // doesn't change the order.
+        let mut sources = std::mem::take(&mut self.sources);
+        let mut css_sources = std::mem::take(&mut self.css_sources);
+
+        let src_path = |asset: &Asset| self.src_path(&asset.path).to_path_buf();
+        sources.sort_by_cached_key(src_path);
+        css_sources.sort_by_cached_key(src_path);
+
+        // JavaScript can reference other assets, like `.wasm` files,
+        // so it goes last for their URLs to be known. Stylesheets are
+        // processed after all of these, in dependency order.
+        sources.sort_by_key(|asset| asset.asset_type.is_javascript());
+
+        self.sources = sources;
+        self.css_sources = css_sources;
+    }
+
+    /// The directories assets are found in, with the overlay first.
+    pub fn dirs(&self) -> impl Iterator<Item = &Path> {
+        self.overlay_dir.iter().chain([&self.src_dir]).map(PathBuf::as_path)
+    }
+
+    /// The absolute path of the assets directory, which `creme::service!()`
+    /// serves in development mode.
+    pub(crate) fn abs_src_dir(&self) -> PathBuf {
+        self.src_dir.absolutize().unwrap().to_path_buf()
+    }
+
+    /// The URL assets are served under in development mode, the name of the
+    /// assets directory, like `assets` or `static`. `creme::service!()`
+    /// strips the same prefix, so the two always match.
+    pub(crate) fn dev_url(&self) -> String {
+        self.abs_src_dir()
+            .file_name()
+            .map_or(DEV_ASSETS_URL.to_string(), |name| {
+                name.to_string_lossy().into_owned()
+            })
+    }
+
+    /// The path of an asset, relative to the directory it's in.
+    pub fn src_path<'a>(&self, path: &'a Path) -> &'a Path {
+        self.dirs()
+            .find_map(|dir| path.strip_prefix(dir).ok())
+            .unwrap()
+    }
+
+    /// Reads the ignore files at the top of an assets directory. Like `git`,
+    /// the patterns are relative to the directory.
+    fn ignore_matcher(dir: &Path, config: &AssetSourceConfig) -> io::Result<Gitignore> {
+        let mut builder = GitignoreBuilder::new(dir);
+
+        for (name, enabled) in [(CREME_IGNORE_FILE, true), (GIT_IGNORE_FILE, config.respect_gitignore)] {
+            let file = dir.join(name);
+
+            if enabled && file.is_file() {
+                if let Some(err) = builder.add(file) {
+                    return Err(io::Error::other(err));
+                }
+            }
+        }
+
+        builder.build().map_err(io::Error::other)
     }
 
     /// Add an asset to the list of assets to be bundled.
     fn add_asset(
         assets: &mut Vec<Asset>,
         css_assets: &mut Vec<Asset>,
-        ignore_leading: &Option<String>,
+        config: &AssetSourceConfig,
         path: impl Into<PathBuf>,
     ) {
         let path: PathBuf = path.into();
 
-        if let Some(leading) = ignore_leading {
+        if path.file_name().is_some_and(|name| name == CREME_IGNORE_FILE || name == GIT_IGNORE_FILE) {
+            return;
+        }
+
+        if let Some(leading) = &config.ignore_leading {
             if path
                 .file_name()
                 .unwrap() // this is always a file
@@ -129,7 +553,7 @@ impl AssetSource {
             }
         }
 
-        let mime = mime_guess::from_path(&path).first_or_octet_stream();
+        let mime = config.guess_mime(&path);
         let asset_type = AssetType::from(mime);
 
         if asset_type == AssetType::Css {
@@ -140,31 +564,98 @@ impl AssetSource {
     }
 
     /// Add all assets in a directory to the bundle.
+    ///
+    /// Symlinks are skipped, unless they're followed. Then, a directory reachable
+    /// through several symlinks is only added once, and a symlink cycle is an error.
+    /// Files and directories matched by `ignore` are skipped.
     fn add_assets(
         assets: &mut Vec<Asset>,
         css_assets: &mut Vec<Asset>,
-        ignore_leading: &Option<String>,
+        config: &AssetSourceConfig,
+        ignore: &Gitignore,
+        walk: &mut DirWalk,
         path: impl Into<PathBuf>,
     ) -> io::Result<()> {
         let path = path.into();
+
+        if !walk.enter(&path)? {
+            walk.leave();
+            return Ok(());
+        }
+
         let dir = fs::read_dir(&path)?;
 
         for entry in dir.flatten() {
+            if walk.skips(&entry)? {
+                continue;
+            }
+
             let path = entry.path();
+            let is_dir = path.is_dir();
+
+            if ignore.matched(&path, is_dir).is_ignore() {
+                continue;
+            }
 
             // Recurse if directory
-            if path.is_dir() {
-                Self::add_assets(assets, css_assets, ignore_leading, path)?;
+            if is_dir {
+                Self::add_assets(assets, css_assets, config, ignore, walk, path)?;
             } else {
-                Self::add_asset(assets, css_assets, ignore_leading, path);
+                Self::add_asset(assets, css_assets, config, path);
             }
         }
 
+        walk.leave();
+
         Ok(())
     }
 }
 
-#[derive(Default, Debug)]
+/// Options for how stylesheets are processed.
+#[derive(Debug, Clone)]
+struct CssConfig {
+    /// Whether to emit source maps for processed CSS.
+    source_maps: bool,
+
+    /// Whether processed CSS is printed minified, rather than indented.
+    minify: bool,
+
+    /// Features always compiled, even when the targets support them.
+    include_features: Features,
+
+    /// Features never compiled, even when the targets don't support them.
+    exclude_features: Features,
+
+    /// Draft syntax the parser accepts.
+    parser_flags: ParserFlags,
+
+    /// Browserslist queries used to pick the browsers CSS is compiled for.
+    browserslist: Vec<String>,
+
+    /// Extra directories that bare `@import` specifiers are resolved against.
+    import_paths: Vec<PathBuf>,
+
+    /// Assets referenced by `url()` that are smaller than this many bytes
+    /// are inlined as data URIs.
+    inline_threshold: Option<usize>,
+}
+
+impl Default for CssConfig {
+    fn default() -> Self {
+        Self {
+            source_maps: false,
+            minify: true,
+            include_features: Features::empty(),
+            exclude_features: Features::empty(),
+            parser_flags: ParserFlags::NESTING | ParserFlags::CUSTOM_MEDIA,
+            browserslist: vec![">= 0.25%".to_string()],
+            import_paths: Vec::new(),
+            inline_threshold: None,
+        }
+    }
+}
+
+#[derive(Default, Debug, Clone)]
 enum ReleaseMode {
     /// The file directory structure is preserved.
     /// Assets are served directly from the source directory.
@@ -172,22 +663,32 @@ enum ReleaseMode {
     #[default]
     Development,
 
-    /// The file directory structure is flattened.
+    /// The file directory structure is optionally flattened.
     /// Files are optionally hashed for cache busting.
     Release { hashed: bool, flatten: bool },
 }
 
 /// The main struct for the library.
 /// This is used to configure the library, and builds a `CremeBundler`.
-#[derive(Debug, Default)]
+#[derive(Debug)]
 pub struct Creme {
     /// The path to the public directory in the project.
     /// This is copied to the dist directory.
     public_dir: Option<PathBuf>,
 
+    /// Whether the public directory is copied to the dist directory in release mode.
+    copy_public: bool,
+
+    /// Paths in the public directory that aren't copied to the dist directory.
+    public_excludes: Vec<PathBuf>,
+
     /// Contains the source directory and the assets to be processed.
     assets: Option<AssetSource>,
 
+    /// The deploy environment, like `staging`, which picks an overlay for the
+    /// assets directory. Defaults to the `CREME_ENV` env var.
+    environment: Option<String>,
+
     /// Path to write the assets to, relative to the out public directory.
     /// Typically, this would be your build's `out_dir/public/assets` folder.
     out_assets_dir: Option<PathBuf>,
@@ -201,6 +702,105 @@ pub struct Creme {
 
     /// How assets are written to the filesystem.
     release_mode: ReleaseMode,
+
+    /// How stylesheets are processed in release mode.
+    css: CssConfig,
+
+    /// Mime types to use instead of guessing, by lowercase file extension.
+    mime_overrides: HashMap<String, Mime>,
+
+    /// Whether to write gzip and brotli versions of the output in release mode.
+    precompress: bool,
+
+    /// The algorithm used to hash output filenames.
+    hash_algorithm: HashAlgorithm,
+
+    /// Where the hash goes in output paths.
+    hash_strategy: HashStrategy,
+
+    /// What output paths are hashed from.
+    hash_source: HashSource,
+
+    /// Where to write the manifest. Defaults to `creme-manifest.json` in the out directory.
+    manifest_path: Option<PathBuf>,
+
+    /// The manifest's file name in the out directory, if `manifest_path` isn't set.
+    manifest_name: Option<String>,
+
+    /// Where to write a Rust module of asset URL constants, if anywhere.
+    /// Relative to the out directory.
+    rust_module: Option<PathBuf>,
+
+    /// Whether to rewrite asset references in public HTML files in release mode.
+    rewrite_html: bool,
+
+    /// Whether to minify public HTML files in release mode.
+    minify_html: bool,
+
+    /// Whether JSON assets are checked to parse in release mode.
+    validate_json: bool,
+
+    /// Whether to minify JSON assets in release mode.
+    minify_json: bool,
+
+    /// Whether symlinks in the assets and public directories are followed.
+    follow_symlinks: bool,
+
+    /// Whether the `.gitignore` in the assets directory is respected.
+    respect_gitignore: bool,
+
+    /// Whether files whose extension gives no mime type get it from their contents.
+    sniff_mime: bool,
+
+    /// Unix permissions to set on written output files.
+    file_mode: Option<u32>,
+
+    /// Directories, relative to the assets directory, whose files are copied
+    /// without being processed.
+    passthrough_dirs: Vec<PathBuf>,
+
+    /// Processors tried before the built-in processing, in order.
+    processors: Vec<Arc<dyn AssetProcessor>>,
+
+    /// Edits the asset URLs before the manifest is written.
+    manifest_hook: Option<ManifestHook>,
+
+    /// A page in the public directory to serve for unknown routes.
+    not_found_page: Option<PathBuf>,
+
+    /// Globs of the assets in each named group, like `icons/*.svg` for `icons`.
+    asset_groups: BTreeMap<String, Vec<String>>,
+
+    /// `Cache-Control` values by glob of served paths, like `robots.txt`.
+    cache_control: Vec<(String, String)>,
+
+    /// Globs of the assets served as downloads, like `docs/*.pdf`.
+    download_globs: Vec<String>,
+
+    /// The largest the output can be in total, in bytes, in release mode.
+    max_total_size: Option<u64>,
+
+    /// The largest any single output file can be, in bytes, in release mode.
+    max_asset_size: Option<u64>,
+
+    /// Assets smaller than this many bytes are inlined as data URIs in release mode.
+    inline_threshold: Option<usize>,
+
+    /// Whether assets nothing references fail the build in release mode.
+    deny_unreferenced: bool,
+
+    /// Whether release bundles update the dist directory in place.
+    incremental: bool,
+
+    /// Generates a stylesheet with Tailwind in release mode.
+    #[cfg(feature = "tailwind")]
+    tailwind: Option<TailwindConfig>,
+}
+
+impl Default for Creme {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl Creme {
@@ -208,11 +808,45 @@ impl Creme {
     pub fn new() -> Self {
         Self {
             public_dir: None,
+            copy_public: true,
+            public_excludes: Vec::new(),
             assets: None,
+            environment: None,
             out_assets_dir: None,
             out_public_dir: None,
             out_dir: None,
             release_mode: ReleaseMode::default(),
+            css: CssConfig::default(),
+            mime_overrides: HashMap::new(),
+            precompress: false,
+            hash_algorithm: HashAlgorithm::default(),
+            hash_strategy: HashStrategy::default(),
+            hash_source: HashSource::default(),
+            manifest_path: None,
+            manifest_name: None,
+            rust_module: None,
+            rewrite_html: false,
+            minify_html: false,
+            validate_json: false,
+            minify_json: false,
+            follow_symlinks: false,
+            respect_gitignore: false,
+            sniff_mime: false,
+            file_mode: None,
+            passthrough_dirs: Vec::new(),
+            processors: Vec::new(),
+            manifest_hook: None,
+            not_found_page: None,
+            asset_groups: BTreeMap::new(),
+            cache_control: Vec::new(),
+            download_globs: Vec::new(),
+            max_total_size: None,
+            max_asset_size: None,
+            inline_threshold: None,
+            deny_unreferenced: false,
+            incremental: false,
+            #[cfg(feature = "tailwind")]
+            tailwind: None,
         }
     }
 
@@ -253,6 +887,18 @@ impl Creme {
     }
 
     /// Sets the release mode to release.
+    ///
+    /// A release bundle writes the dist directory and the module that embeds it,
+    /// which is made from the files in the dist directory, so the hashes match.
+    /// To also upload the assets, like to a CDN, upload the dist directory after
+    /// bundling. See [`CremeBundler::dist_dir`].
+    ///
+    /// ```rust,ignore
+    /// let bundler = Creme::new().default_config()?.release().build()?;
+    /// bundler.bundle()?;
+    ///
+    /// upload_to_cdn(&bundler.dist_dir())?;
+    /// ```
     pub fn release(self) -> Self {
         Self {
             release_mode: ReleaseMode::Release {
@@ -263,6 +909,63 @@ impl Creme {
         }
     }
 
+    /// Sets whether output filenames get a content hash, for cache busting.
+    /// Only applies in release mode, where it's enabled by default.
+    pub fn hashed(self, hashed: bool) -> Self {
+        match self.release_mode {
+            ReleaseMode::Release { flatten, .. } => Self {
+                release_mode: ReleaseMode::Release { hashed, flatten },
+                ..self
+            },
+            ReleaseMode::Development => self,
+        }
+    }
+
+    /// Sets the algorithm used to hash output filenames. The default is blake3.
+    pub fn hash_algorithm(self, hash_algorithm: HashAlgorithm) -> Self {
+        Self {
+            hash_algorithm,
+            ..self
+        }
+    }
+
+    /// Sets where the hash goes in asset URLs. By default it's added to the
+    /// filename, like `cat-1a2b3c4d.jpeg`. Only applies when hashing is enabled.
+    pub fn hash_strategy(self, hash_strategy: HashStrategy) -> Self {
+        Self {
+            hash_strategy,
+            ..self
+        }
+    }
+
+    /// Sets what output paths are hashed from. By default it's the processed
+    /// output, so a hash always matches what's served, and `HashSource::Original`
+    /// hashes the sources instead, so hashes don't change with toolchain upgrades.
+    /// See [`HashSource`] for the tradeoff.
+    pub fn hash_source(self, hash_source: HashSource) -> Self {
+        Self {
+            hash_source,
+            ..self
+        }
+    }
+
+    /// Sets whether assets are all written to the top of the assets directory,
+    /// rather than keeping the directory they were in.
+    /// Only applies in release mode, where it's enabled by default.
+    ///
+    /// Different files with the same name, like `a/logo.png` and `b/logo.png`,
+    /// can end up with the same output name when hashing is disabled.
+    /// Bundling then fails with [`CremeError::OutputCollision`].
+    pub fn flatten(self, flatten: bool) -> Self {
+        match self.release_mode {
+            ReleaseMode::Release { hashed, .. } => Self {
+                release_mode: ReleaseMode::Release { hashed, flatten },
+                ..self
+            },
+            ReleaseMode::Development => self,
+        }
+    }
+
     /// Sets the release mode to development.
     pub fn development(self) -> Self {
         Self {
@@ -271,92 +974,756 @@ impl Creme {
         }
     }
 
-    /// Sets the public directory.
-    /// The public directory is copied to the dist directory.
-    /// The default public directory is `public`.
-    pub fn set_public_dir(self, public_dir: impl Into<PathBuf>) -> Self {
+    /// Enables or disables CSS source maps.
+    /// When enabled, each processed stylesheet gets a `.map` file written next to it,
+    /// and a `sourceMappingURL` comment pointing to it. Only applies in release mode.
+    pub fn css_source_maps(self, enabled: bool) -> Self {
         Self {
-            public_dir: Some(public_dir.into()),
+            css: CssConfig {
+                source_maps: enabled,
+                ..self.css
+            },
             ..self
         }
     }
 
-    /// Sets the directory to write the assets to.
-    /// The default assets directory is `assets`.
-    pub fn set_out_assets_dir(self, out_assets_dir: impl Into<PathBuf>) -> Self {
+    /// Enables or disables minifying processed CSS. Disabling it prints readable,
+    /// indented CSS, which helps debugging a release build, especially with
+    /// source maps. Imports are still bundled, URLs rewritten, and the CSS compiled
+    /// for the browserslist targets. Enabled by default. Only applies in release mode.
+    pub fn css_minify(self, enabled: bool) -> Self {
         Self {
-            out_assets_dir: Some(out_assets_dir.into()),
+            css: CssConfig {
+                minify: enabled,
+                ..self.css
+            },
             ..self
         }
     }
 
-    /// Sets the directory to write the dist to.
-    /// The default output directory is `dist`.
-    pub fn set_out_public_dir(self, out_public_dir: impl Into<PathBuf>) -> Self {
+    /// Enables or disables adding and removing vendor prefixes, like `-webkit-`,
+    /// for the browserslist targets. Enabled by default. When disabled, prefixes
+    /// are left as they're written. Only applies in release mode.
+    pub fn css_autoprefix(self, enabled: bool) -> Self {
+        let mut exclude_features = self.css.exclude_features;
+        exclude_features.set(Features::VendorPrefixes, !enabled);
+
         Self {
-            out_public_dir: Some(out_public_dir.into()),
+            css: CssConfig {
+                exclude_features,
+                ..self.css
+            },
             ..self
         }
     }
 
-    /// Sets the directory to write the output to.
-    /// The default output directory is the `OUT_DIR` env var set by Cargo.
-    pub fn out_dir(self, out_dir: impl Into<PathBuf>) -> Self {
-        let out_dir: PathBuf = out_dir.into();
-        let out_dir = out_dir.absolutize().unwrap().to_path_buf();
-
+    /// Sets the CSS features that are always compiled, even when the browserslist
+    /// targets support them, like `Features::Nesting`. Only applies in release mode.
+    pub fn css_include_features(self, features: Features) -> Self {
         Self {
-            out_dir: Some(out_dir),
+            css: CssConfig {
+                include_features: features,
+                ..self.css
+            },
             ..self
         }
     }
 
-    /// Sets the output directory to the `OUT_DIR` env var set by Cargo.
-    /// This is useful when you want to embed the assets in the binary,
-    /// and putting the output files into a directory that won't litter
-    /// the project directory.
-    ///
-    /// # Errors
-    ///
-    /// This function will return an error if the `OUT_DIR` environment variable is not set.
-    pub fn out_dir_build_rs(self) -> CremeResult<Self> {
-        Ok(Self {
-            out_dir: Some(PathBuf::from(std::env::var("OUT_DIR")?)),
+    /// Sets the CSS features that are never compiled, even when the browserslist
+    /// targets don't support them, like `Features::Colors`. This replaces the
+    /// features [`Creme::css_autoprefix`] excludes. Only applies in release mode.
+    pub fn css_exclude_features(self, features: Features) -> Self {
+        Self {
+            css: CssConfig {
+                exclude_features: features,
+                ..self.css
+            },
             ..self
-        })
+        }
     }
 
-    pub fn set_assets_dir(self, assets_dir: impl Into<PathBuf>) -> CremeResult<Self> {
-        Ok(Self {
-            assets: Some(AssetSource::from_asset_dir(assets_dir)?),
+    /// Sets the draft CSS syntax stylesheets may use. By default that's nesting
+    /// and custom media queries, `ParserFlags::NESTING | ParserFlags::CUSTOM_MEDIA`.
+    pub fn css_parser_flags(self, flags: ParserFlags) -> Self {
+        Self {
+            css: CssConfig {
+                parser_flags: flags,
+                ..self.css
+            },
             ..self
-        })
+        }
     }
 
-    pub fn build(self) -> CremeResult<CremeBundler> {
-        let Creme {
-            public_dir,
-            assets,
+    /// Inlines assets referenced by `url()` in stylesheets as base64 data URIs when
+    /// they're smaller than `threshold` bytes, saving a request for small images.
+    /// Larger assets are still referenced by their URL. Only applies in release mode.
+    ///
+    /// Unlike [`Creme::inline_threshold`], the assets are still written, so
+    /// `asset!()` points at a file. When both are set, stylesheets inline assets
+    /// under either threshold.
+    pub fn css_inline_threshold(self, threshold: usize) -> Self {
+        Self {
+            css: CssConfig {
+                inline_threshold: Some(threshold),
+                ..self.css
+            },
+            ..self
+        }
+    }
+
+    /// Sets the browserslist queries used to determine which browsers to compile CSS for.
+    /// The default query is `>= 0.25%`.
+    pub fn browserslist<S: Into<String>>(self, queries: impl IntoIterator<Item = S>) -> Self {
+        Self {
+            css: CssConfig {
+                browserslist: queries.into_iter().map(Into::into).collect(),
+                ..self.css
+            },
+            ..self
+        }
+    }
+
+    /// Sets extra directories to resolve CSS `@import`s against, such as `node_modules`.
+    /// Imports relative to the importing file are always tried first.
+    pub fn css_import_paths<P: Into<PathBuf>>(self, paths: impl IntoIterator<Item = P>) -> Self {
+        Self {
+            css: CssConfig {
+                import_paths: paths.into_iter().map(Into::into).collect(),
+                ..self.css
+            },
+            ..self
+        }
+    }
+
+    /// Overrides the mime type for files with the given extension,
+    /// for files that aren't recognized or are guessed wrong.
+    /// This applies to both assets and public files, in every mode.
+    pub fn mime_override(mut self, ext: impl AsRef<str>, mime: Mime) -> Self {
+        let ext = ext.as_ref().trim_start_matches('.').to_lowercase();
+        self.mime_overrides.insert(ext, mime);
+        self
+    }
+
+    /// Enables or disables precompression. When enabled, a gzip and brotli
+    /// version of each text file is written next to it, and embedded with it.
+    /// The release service sends them to clients that accept them.
+    /// Only applies in release mode.
+    pub fn precompress(self, enabled: bool) -> Self {
+        Self {
+            precompress: enabled,
+            ..self
+        }
+    }
+
+    /// Enables or disables rewriting asset references in public HTML files.
+    /// When enabled, `src`, `href`, `poster` and `srcset` attributes that point to
+    /// an asset's development URL, like `/assets/img/cat.jpeg`, are rewritten to
+    /// its output URL. Only applies in release mode.
+    pub fn rewrite_html(self, enabled: bool) -> Self {
+        Self {
+            rewrite_html: enabled,
+            ..self
+        }
+    }
+
+    /// Enables or disables minifying public HTML files. Comments are removed and
+    /// whitespace is collapsed, except inside `pre`, `textarea`, `script` and
+    /// `style`. Only applies in release mode.
+    pub fn minify_html(self, enabled: bool) -> Self {
+        Self {
+            minify_html: enabled,
+            ..self
+        }
+    }
+
+    /// Enables or disables checking that JSON assets parse, so a malformed data
+    /// file fails the build instead of the app. Only applies in release mode.
+    pub fn validate_json(self, enabled: bool) -> Self {
+        Self {
+            validate_json: enabled,
+            ..self
+        }
+    }
+
+    /// Enables or disables minifying JSON assets, by removing whitespace between
+    /// values. They're validated first. Only applies in release mode.
+    pub fn minify_json(self, enabled: bool) -> Self {
+        Self {
+            minify_json: enabled,
+            ..self
+        }
+    }
+
+    /// Generates the `input` stylesheet with the Tailwind CLI in release mode,
+    /// scanning the files matched by `content_globs` for class names.
+    /// `tailwindcss` has to be on the `PATH`.
+    #[cfg(feature = "tailwind")]
+    pub fn tailwind(self, config: TailwindConfig) -> Self {
+        Self {
+            tailwind: Some(config),
+            ..self
+        }
+    }
+
+    /// Enables or disables following symlinks in the assets and public directories.
+    /// By default symlinks are skipped, so nothing outside of them is read.
+    /// When enabled, a directory linked more than once is only bundled once,
+    /// and a symlink cycle is an error.
+    pub fn follow_symlinks(self, enabled: bool) -> Self {
+        Self {
+            follow_symlinks: enabled,
+            ..self
+        }
+    }
+
+    /// Enables or disables skipping files matched by the `.gitignore` at the top
+    /// of the assets directory. Files matched by a `.cremeignore` there are always
+    /// skipped, as are files starting with `_`. Nested ignore files aren't read.
+    pub fn respect_gitignore(self, enabled: bool) -> Self {
+        Self {
+            respect_gitignore: enabled,
+            ..self
+        }
+    }
+
+    /// Enables or disables guessing the mime type of files whose extension gives
+    /// none, or that have no extension, from their first bytes, like `image/png`
+    /// for a PNG named `logo` or `logo.dat`. Mime overrides still take precedence.
+    /// Common image, font, audio, video and archive formats are recognized,
+    /// with the `infer` crate. Otherwise, and by default, they're
+    /// `application/octet-stream`. Needs the `sniff` feature.
+    #[cfg(feature = "sniff")]
+    pub fn sniff_mime(self, enabled: bool) -> Self {
+        Self {
+            sniff_mime: enabled,
+            ..self
+        }
+    }
+
+    /// Sets the unix permissions of every output file, like `0o644` to make
+    /// them world-readable regardless of the umask. Does nothing on other platforms.
+    pub fn file_mode(self, mode: u32) -> Self {
+        Self {
+            file_mode: Some(mode),
+            ..self
+        }
+    }
+
+    /// Marks a directory, relative to the assets directory, as passthrough.
+    /// Files in it are copied byte-for-byte instead of being processed, like
+    /// already minified CSS in `vendor`. They're still hashed in release mode.
+    /// Since their contents aren't touched, `url()`s in passthrough stylesheets
+    /// aren't rewritten to hashed names.
+    pub fn passthrough_dir(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.passthrough_dirs.push(dir.into());
+        self
+    }
+
+    /// Sets a page in the public directory, like `404.html`, that
+    /// `creme::service!()` serves with a 404 status for unknown routes.
+    /// Unknown assets still get an empty 404. The page isn't used when
+    /// the service has a fallback, since that handles unknown routes instead.
+    pub fn not_found_page(self, page: impl Into<PathBuf>) -> Self {
+        Self {
+            not_found_page: Some(page.into()),
+            ..self
+        }
+    }
+
+    /// Adds the assets matching `glob`, like `icons/*.svg`, to a named group.
+    /// `creme::manifest_group!("icons")` returns the URLs of every asset in it.
+    /// Globs match paths in the assets directory, and `*` doesn't cross
+    /// directories, so use `**` to match nested files. Calling this again
+    /// with the same name adds to the group.
+    pub fn asset_group(mut self, name: impl Into<String>, glob: impl Into<String>) -> Self {
+        self.asset_groups
+            .entry(name.into())
+            .or_default()
+            .push(glob.into());
+        self
+    }
+
+    /// Sets the `Cache-Control` header the release service sends for files whose
+    /// path matches `glob`, like `public, max-age=3600` for `robots.txt`.
+    ///
+    /// Globs match the path files are served at, relative to the public directory,
+    /// like `favicon.ico` or `assets/**/*.woff2`. The first matching rule wins.
+    /// Other files keep the default, which is to cache hashed assets forever
+    /// and revalidate everything else. The values are also recorded in the
+    /// manifest. Files served from disk in development mode don't use them.
+    pub fn cache_control(mut self, glob: impl Into<String>, value: impl Into<String>) -> Self {
+        self.cache_control.push((glob.into(), value.into()));
+        self
+    }
+
+    /// Serves the assets matching any of `globs`, like `docs/*.pdf`, as downloads.
+    /// The release service sends them with `Content-Disposition: attachment`,
+    /// named after the original file rather than the hashed one, like `guide.pdf`.
+    ///
+    /// Globs match asset paths, like asset groups. The names are also recorded
+    /// in the manifest. Files served from disk in development mode don't use them.
+    pub fn download_globs<S: Into<String>>(mut self, globs: impl IntoIterator<Item = S>) -> Self {
+        self.download_globs
+            .extend(globs.into_iter().map(Into::into));
+        self
+    }
+
+    /// Registers a processor for the assets it handles, like a SCSS compiler.
+    /// Processors are tried in the order they're registered, before the
+    /// built-in processing. See [`AssetProcessor`].
+    pub fn with_processor(mut self, processor: Box<dyn AssetProcessor>) -> Self {
+        self.processors.push(Arc::from(processor));
+        self
+    }
+
+    /// Calls `hook` with each asset's URL, by its path in the assets directory,
+    /// before the manifest is written, in both release and development mode.
+    /// URLs it adds, like ones for files another tool generates, changes or
+    /// removes are what `asset!()` sees. It's called on every bundle, so with
+    /// `watch`, it can be called more than once.
+    ///
+    /// ```rust,ignore
+    /// Creme::new().manifest_hook(|urls| {
+    ///     urls.insert("js/app.js".to_string(), "assets/app-1a2b3c4d.js".to_string());
+    /// })
+    /// ```
+    pub fn manifest_hook(
+        self,
+        hook: impl Fn(&mut HashMap<String, String>) + Send + Sync + 'static,
+    ) -> Self {
+        Self {
+            manifest_hook: Some(ManifestHook(Arc::new(hook))),
+            ..self
+        }
+    }
+
+    /// Fails the release build with `CremeError::BudgetExceeded` if the output
+    /// adds up to more than `bytes`. Files copied from the public directory count
+    /// too, and assets sharing an output are only counted once.
+    pub fn max_total_size(self, bytes: u64) -> Self {
+        Self {
+            max_total_size: Some(bytes),
+            ..self
+        }
+    }
+
+    /// Fails the release build with `CremeError::BudgetExceeded`, naming the
+    /// file, if any output is larger than `bytes`, like a video that was
+    /// committed by accident. Files copied from the public directory count too.
+    pub fn max_asset_size(self, bytes: u64) -> Self {
+        Self {
+            max_asset_size: Some(bytes),
+            ..self
+        }
+    }
+
+    /// Inlines assets smaller than `bytes` as base64 data URIs in release mode,
+    /// like small icons, saving a request for each. Their manifest URL is the
+    /// data URI, so `asset!()` and rewritten `url()`s use it, and no file is written.
+    ///
+    /// Stylesheets and scripts aren't inlined, since they can point at other
+    /// assets by relative URL. For inlining only in stylesheets, see
+    /// [`Creme::css_inline_threshold`]. When both are set, stylesheets inline
+    /// assets under either threshold, since these have no file to point at.
+    pub fn inline_threshold(self, bytes: usize) -> Self {
+        Self {
+            inline_threshold: Some(bytes),
+            ..self
+        }
+    }
+
+    /// Fails the release build with `CremeError::UnreferencedAsset`, listing
+    /// them, if any assets are never referenced, like leftovers from a redesign.
+    ///
+    /// An asset counts as referenced if its path is a string literal in the
+    /// crate's `src` directory, like in `asset!("img/cat.jpeg")`, if it's in
+    /// an asset group whose name is one, or if another stylesheet, script or
+    /// page points at it. Literals in comments don't count. While this is
+    /// enabled, the build script reruns whenever a source file changes.
+    pub fn deny_unreferenced(self, enabled: bool) -> Self {
+        Self {
+            deny_unreferenced: enabled,
+            ..self
+        }
+    }
+
+    /// Updates the dist directory in place in release mode, instead of removing
+    /// it before every bundle. Outputs that already have the right content
+    /// aren't rewritten, so their mtimes are kept, and outputs the bundle
+    /// didn't write are removed afterwards, see [`CremeBundler::clean_stale`].
+    ///
+    /// Pages rewritten with `rewrite_html` or `minify_html` are still written
+    /// on every bundle, since they're processed after they're copied.
+    pub fn incremental(self, enabled: bool) -> Self {
+        Self {
+            incremental: enabled,
+            ..self
+        }
+    }
+
+    /// Sets the public directory.
+    /// The public directory is copied to the dist directory.
+    /// The default public directory is `public`. If it doesn't exist,
+    /// there's nothing to copy, so it's skipped.
+    pub fn set_public_dir(self, public_dir: impl Into<PathBuf>) -> Self {
+        Self {
+            public_dir: Some(public_dir.into()),
+            ..self
+        }
+    }
+
+    /// Bundles without a public directory, so only assets are written.
+    /// Call this after `default_config`, which sets the public directory.
+    pub fn no_public_dir(self) -> Self {
+        Self {
+            public_dir: None,
+            ..self
+        }
+    }
+
+    /// Enables or disables copying the public directory to the dist directory in
+    /// release mode, for public files that are served from disk instead of being
+    /// embedded. Assets are still bundled. The default is to copy it.
+    ///
+    /// The not found page is in the public directory, so it isn't embedded either.
+    pub fn copy_public(self, enabled: bool) -> Self {
+        Self {
+            copy_public: enabled,
+            ..self
+        }
+    }
+
+    /// Leaves a file or directory in the public directory, like `downloads`,
+    /// out of the dist directory in release mode.
+    pub fn exclude_public(mut self, path: impl Into<PathBuf>) -> Self {
+        self.public_excludes.push(path.into());
+        self
+    }
+
+    /// Sets the directory to write the assets to.
+    /// The default assets directory is `assets`.
+    pub fn set_out_assets_dir(self, out_assets_dir: impl Into<PathBuf>) -> Self {
+        Self {
+            out_assets_dir: Some(out_assets_dir.into()),
+            ..self
+        }
+    }
+
+    /// Sets the directory to write the dist to.
+    /// The default output directory is `dist`.
+    pub fn set_out_public_dir(self, out_public_dir: impl Into<PathBuf>) -> Self {
+        Self {
+            out_public_dir: Some(out_public_dir.into()),
+            ..self
+        }
+    }
+
+    /// Sets the directory to write the output to.
+    /// The default output directory is the `OUT_DIR` env var set by Cargo.
+    pub fn out_dir(self, out_dir: impl Into<PathBuf>) -> Self {
+        let out_dir: PathBuf = out_dir.into();
+        let out_dir = out_dir.absolutize().unwrap().to_path_buf();
+
+        Self {
+            out_dir: Some(out_dir),
+            ..self
+        }
+    }
+
+    /// Sets where the manifest is written, for tools that read it outside of the
+    /// `creme` macros. The default is `creme-manifest.json` in the out directory.
+    pub fn manifest_path(self, manifest_path: impl Into<PathBuf>) -> Self {
+        let manifest_path: PathBuf = manifest_path.into();
+        let manifest_path = manifest_path.absolutize().unwrap().to_path_buf();
+
+        Self {
+            manifest_path: Some(manifest_path),
+            ..self
+        }
+    }
+
+    /// Sets the manifest's file name in the out directory, instead of
+    /// `creme-manifest.json`, so several bundles can share an out directory.
+    /// The `creme` macros find it either way. `manifest_path` takes precedence.
+    pub fn manifest_name(self, name: impl Into<String>) -> Self {
+        Self {
+            manifest_name: Some(name.into()),
+            ..self
+        }
+    }
+
+    /// Also writes a Rust module with a constant for each asset's URL, like
+    /// `pub const CSS_STYLE_CSS: &str = "assets/css/style-1a2b3c4d.css";`.
+    /// A relative path is relative to the out directory, so the module can be
+    /// included with `include!(concat!(env!("OUT_DIR"), "/assets.rs"))`.
+    ///
+    /// Constant names are the asset path in uppercase, with anything that isn't
+    /// a letter or digit replaced by an underscore.
+    pub fn emit_rust_module(self, path: impl Into<PathBuf>) -> Self {
+        Self {
+            rust_module: Some(path.into()),
+            ..self
+        }
+    }
+
+    /// Sets the output directory to the `OUT_DIR` env var set by Cargo.
+    /// This is useful when you want to embed the assets in the binary,
+    /// and putting the output files into a directory that won't litter
+    /// the project directory.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if the `OUT_DIR` environment variable is not set.
+    pub fn out_dir_build_rs(self) -> CremeResult<Self> {
+        Ok(Self {
+            out_dir: Some(PathBuf::from(std::env::var("OUT_DIR")?)),
+            ..self
+        })
+    }
+
+    /// Like `out_dir_build_rs`, but uses `default` when `OUT_DIR` is not set,
+    /// so the same setup works in a build script and in a regular binary,
+    /// like a custom CLI.
+    pub fn out_dir_or(self, default: impl Into<PathBuf>) -> Self {
+        match std::env::var_os("OUT_DIR") {
+            Some(out_dir) => self.out_dir(out_dir),
+            None => self.out_dir(default),
+        }
+    }
+
+    /// Sets the deploy environment, like `staging` or `production`. Files in the
+    /// environment's overlay directory, named after the assets directory with
+    /// the environment added, like `assets.staging`, take the place of the
+    /// assets at the same path. The overlay doesn't need to exist.
+    ///
+    /// Without this, the environment is read from the `CREME_ENV` env var.
+    pub fn environment(self, environment: impl Into<String>) -> Self {
+        Self {
+            environment: Some(environment.into()),
+            ..self
+        }
+    }
+
+    /// Sets the assets directory, and finds the assets in it.
+    /// The default assets directory is `assets`.
+    ///
+    /// Files matched by a `.cremeignore` at the top of the assets directory,
+    /// in `.gitignore` syntax, are left out of the bundle and the manifest,
+    /// like scratch files:
+    ///
+    /// ```text
+    /// *.tmp
+    /// *.swp
+    /// drafts/
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// This will return an error if the assets directory doesn't exist.
+    pub fn set_assets_dir(self, assets_dir: impl Into<PathBuf>) -> CremeResult<Self> {
+        let assets_dir: PathBuf = assets_dir.into();
+        if !assets_dir.is_dir() {
+            return Err(CremeError::AssetsDirDoesNotExist(assets_dir));
+        }
+
+        Ok(Self {
+            assets: Some(AssetSource::from_asset_dir(assets_dir)?),
+            ..self
+        })
+    }
+
+    /// Sets the assets directory to one compiled into a crate with
+    /// `include_dir::include_dir!`, like a library's own vendored assets,
+    /// so they're bundled without the consumer providing a directory.
+    ///
+    /// Assets are processed from disk, so they're written to `creme-include`
+    /// in the out directory first. Set the out directory before calling this,
+    /// or `OUT_DIR` is used.
+    #[cfg(feature = "include_dir")]
+    pub fn set_assets_include_dir(self, dir: &include_dir::Dir<'_>) -> CremeResult<Self> {
+        let out_dir = match &self.out_dir {
+            Some(out_dir) => out_dir.clone(),
+            None => PathBuf::from(std::env::var("OUT_DIR")?),
+        };
+        let extract_dir = out_dir.join(INCLUDE_DIR);
+
+        included::extract(dir, &extract_dir)?;
+        self.set_assets_dir(extract_dir)
+    }
+
+    pub fn build(self) -> CremeResult<CremeBundler> {
+        let Creme {
+            public_dir,
+            copy_public,
+            public_excludes,
+            assets,
+            environment,
             out_assets_dir,
             out_public_dir,
             out_dir,
             release_mode,
+            css,
+            mime_overrides,
+            precompress,
+            hash_algorithm,
+            hash_strategy,
+            hash_source,
+            manifest_path,
+            manifest_name,
+            rust_module,
+            rewrite_html,
+            minify_html,
+            validate_json,
+            minify_json,
+            follow_symlinks,
+            respect_gitignore,
+            sniff_mime,
+            file_mode,
+            passthrough_dirs,
+            processors,
+            manifest_hook,
+            not_found_page,
+            asset_groups,
+            cache_control,
+            download_globs,
+            max_total_size,
+            max_asset_size,
+            inline_threshold,
+            deny_unreferenced,
+            incremental,
+            #[cfg(feature = "tailwind")]
+            tailwind,
         } = self;
 
-        let assets = assets.unwrap();
+        let mut assets = assets.unwrap();
         let out_public_dir = out_public_dir.unwrap();
         let out_assets_dir = out_assets_dir.unwrap();
-        let public_dir = public_dir.unwrap();
+        let public_dir = public_dir.filter(|dir| dir.is_dir());
         let out_dir = out_dir.unwrap();
+        let manifest_path = manifest_path
+            .unwrap_or_else(|| out_dir.join(manifest_name.as_deref().unwrap_or(MANIFEST_FILE)));
+        let rust_module = rust_module.map(|path| out_dir.join(path));
+
+        // The assets were scanned before the overrides were known.
+        if !mime_overrides.is_empty()
+            || follow_symlinks
+            || respect_gitignore
+            || sniff_mime
+        {
+            let source_config = AssetSourceConfig {
+                mime_overrides,
+                follow_symlinks,
+                respect_gitignore,
+                #[cfg(feature = "sniff")]
+                sniff_mime,
+                ..assets.source_config
+            };
+            assets = AssetSource::with_config(assets.src_dir, source_config)?;
+        }
+
+        let environment = environment.or_else(|| std::env::var("CREME_ENV").ok());
+        if let Some(environment) = &environment {
+            let overlay_dir = overlay_dir(&assets.src_dir, environment);
+
+            if overlay_dir.is_dir() {
+                assets = assets.with_overlay(overlay_dir)?;
+            } else {
+                tracing::debug!(dir = %overlay_dir.display(), "no overlay for environment {environment}");
+            }
+        }
+
+        if let Some(page) = &not_found_page {
+            match &public_dir {
+                Some(dir) if dir.join(page).is_file() => {}
+                Some(dir) => return Err(CremeError::NotAFile(dir.join(page))),
+                None => return Err(CremeError::NotAFile(page.clone())),
+            }
+        }
+
+        let asset_groups = asset_groups
+            .into_iter()
+            .map(|(name, globs)| Ok((name, glob_set(&globs)?)))
+            .collect::<CremeResult<Vec<_>>>()?;
+        let cache_control = CacheControlRules::new(cache_control)?;
+        let downloads = glob_set(&download_globs)?;
+
+        #[cfg(feature = "tailwind")]
+        if let Some(tailwind) = &tailwind {
+            if !tailwind.input.is_file() {
+                return Err(CremeError::NotAFile(tailwind.input.clone()));
+            }
+        }
+
+        // Usually the wrong directory, but an empty one is still valid.
+        if assets.sources.is_empty() && assets.css_sources.is_empty() {
+            let message = format!("no assets found in {}", assets.src_dir.display());
+            tracing::warn!("{message}");
+
+            if std::env::var("OUT_DIR").is_ok() {
+                println!("cargo:warning={message}");
+            }
+        }
 
         if std::env::var("OUT_DIR").is_ok() {
+            println!("cargo:rerun-if-env-changed=CREME_ENV");
+
+            // An overlay that doesn't exist yet can still be created.
+            if let Some(environment) = &environment {
+                let overlay_dir = overlay_dir(&assets.src_dir, environment);
+                if !overlay_dir.is_dir() {
+                    println!("cargo:rerun-if-changed={}", overlay_dir.display());
+                }
+            }
+
+            if let Some(overlay_dir) = &assets.overlay_dir {
+                rerun_if_changed(overlay_dir, &mut DirWalk::new(follow_symlinks))?;
+            }
+
+            if let Some(page) = &not_found_page {
+                println!("cargo:rustc-env=CREME_NOT_FOUND_PAGE={}", paths::to_url(page));
+            }
+
+            println!(
+                "cargo:rustc-env=CREME_MIME_OVERRIDES={}",
+                serde_json::to_string(&mime_override_strings(&assets.source_config))?
+            );
+            println!(
+                "cargo:rustc-env=CREME_MANIFEST={}",
+                manifest_path.display()
+            );
+
             match release_mode {
                 ReleaseMode::Release {
                     hashed: _,
                     flatten: _,
                 } => {
-                    println!("cargo:rerun-if-changed={}", assets.src_dir.display());
-                    println!("cargo:rerun-if-changed={}", public_dir.display());
+                    rerun_if_changed(&assets.src_dir, &mut DirWalk::new(follow_symlinks))?;
+                    if let Some(public_dir) = public_dir.as_ref().filter(|_| copy_public) {
+                        rerun_if_changed(public_dir, &mut DirWalk::new(follow_symlinks))?;
+                    }
+
+                    // References to assets can come and go with any source file.
+                    if deny_unreferenced {
+                        let source_dir = Path::new(references::SOURCE_DIR);
+                        if source_dir.is_dir() {
+                            rerun_if_changed(source_dir, &mut DirWalk::new(follow_symlinks))?;
+                        }
+                    }
+
+                    // Stylesheets from import paths are bundled in, so they count too.
+                    for import_path in css.import_paths.iter().filter(|path| path.is_dir()) {
+                        rerun_if_changed(import_path, &mut DirWalk::new(follow_symlinks))?;
+                    }
+
+                    // Tailwind's output depends on the classes used in templates.
+                    #[cfg(feature = "tailwind")]
+                    for content_dir in tailwind.iter().flat_map(TailwindConfig::content_dirs) {
+                        if content_dir.is_dir() {
+                            rerun_if_changed(&content_dir, &mut DirWalk::new(follow_symlinks))?;
+                        } else if content_dir.is_file() {
+                            println!("cargo:rerun-if-changed={}", content_dir.display());
+                        }
+                    }
                     println!(
                         "cargo:rustc-env=CREME_PUBLIC_DIR={}",
                         out_dir.join(&out_public_dir).display()
@@ -366,21 +1733,37 @@ impl Creme {
                         out_dir.join(&out_public_dir).join(&out_assets_dir).display()
                     );
                     println!(
-                        "cargo:rustc-env=CREME_MANIFEST={}",
-                        out_dir.join("creme-manifest.json").display()
+                        "cargo:rustc-env=CREME_EMBED={}",
+                        out_dir.join(EMBED_FILE).display()
                     );
                     println!("cargo:rustc-env=CREME_RELEASE_MODE=release");
                 }
                 ReleaseMode::Development => {
+                    // Public files are served straight from disk, but the
+                    // manifest lists every asset, so it has to stay current.
+                    rerun_if_changed(&assets.src_dir, &mut DirWalk::new(follow_symlinks))?;
+
                     let base_dir = std::env::current_dir()?;
                     println!(
                         "cargo:rustc-env=CREME_PUBLIC_DIR={}",
-                        base_dir.join(&public_dir).display()
+                        match &public_dir {
+                            Some(public_dir) => base_dir.join(public_dir),
+                            // The dev service still needs a directory. The dist
+                            // directory is only written in release mode.
+                            None => out_dir.join(&out_public_dir),
+                        }
+                        .display()
                     );
                     println!(
                         "cargo:rustc-env=CREME_ASSETS_DIR={}",
-                        base_dir.join(&assets.src_dir).display()
+                        assets.abs_src_dir().display()
                     );
+                    if let Some(overlay_dir) = &assets.overlay_dir {
+                        println!(
+                            "cargo:rustc-env=CREME_ASSETS_OVERLAY_DIR={}",
+                            base_dir.join(overlay_dir).display()
+                        );
+                    }
                     println!("cargo:rustc-env=CREME_RELEASE_MODE=development");
                 }
             };
@@ -388,11 +1771,38 @@ impl Creme {
 
         Ok(CremeBundler {
             public_dir,
+            copy_public,
+            public_excludes,
             assets,
             out_assets_dir,
             out_public_dir,
             out_dir,
             release_mode,
+            css,
+            precompress,
+            hash_algorithm,
+            hash_strategy,
+            hash_source,
+            manifest_path,
+            rust_module,
+            rewrite_html,
+            minify_html,
+            validate_json,
+            minify_json,
+            file_mode,
+            passthrough_dirs,
+            processors,
+            manifest_hook,
+            asset_groups,
+            cache_control,
+            downloads,
+            max_total_size,
+            max_asset_size,
+            inline_threshold,
+            deny_unreferenced,
+            incremental,
+            #[cfg(feature = "tailwind")]
+            tailwind,
         })
     }
 
@@ -402,40 +1812,415 @@ impl Creme {
     }
 }
 
+/// Compiles an asset group's globs.
+fn glob_set(globs: &[String]) -> CremeResult<GlobSet> {
+    let mut builder = GlobSetBuilder::new();
+
+    for pattern in globs {
+        builder.add(glob(pattern)?);
+    }
+
+    Ok(builder.build()?)
+}
+
+/// Compiles a glob of paths, where `*` doesn't match across directories.
+pub(crate) fn glob(pattern: &str) -> CremeResult<Glob> {
+    Ok(GlobBuilder::new(pattern).literal_separator(true).build()?)
+}
+
+/// Whether the file at `path` already has this content. Sizes are compared
+/// first, so most changed files aren't read.
+pub(crate) fn is_unchanged(path: &Path, content: &[u8]) -> bool {
+    match fs::metadata(path) {
+        Ok(metadata) if metadata.is_file() && metadata.len() == content.len() as u64 => {
+            fs::read(path).is_ok_and(|existing| existing == content)
+        }
+        _ => false,
+    }
+}
+
+/// The overlay directory for an environment, like `assets.staging` for `assets`.
+fn overlay_dir(src_dir: &Path, environment: &str) -> PathBuf {
+    let mut name = src_dir.file_name().unwrap_or_default().to_os_string();
+    name.push(".");
+    name.push(environment);
+    src_dir.with_file_name(name)
+}
+
+/// Removes the empty directories inside `dir`, like the ones left behind by
+/// directory hashing, without removing `dir` itself.
+fn remove_empty_dirs(dir: &Path) -> io::Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+
+        if path.is_dir() {
+            remove_empty_dirs(&path)?;
+
+            if fs::read_dir(&path)?.next().is_none() {
+                fs::remove_dir(&path)?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Sets the permissions of an output file. Only unix has a file mode,
+/// so this does nothing elsewhere.
+pub(crate) fn set_file_mode(path: &Path, mode: Option<u32>) -> io::Result<()> {
+    #[cfg(unix)]
+    if let Some(mode) = mode {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(path, fs::Permissions::from_mode(mode))?;
+    }
+
+    #[cfg(not(unix))]
+    let _ = (path, mode);
+
+    Ok(())
+}
+
+/// Tells Cargo to rerun the build script when anything in `dir` changes.
+/// Every file is listed individually, since Cargo doesn't reliably notice
+/// changes deep inside a directory. Directories are listed too, so new files
+/// are picked up. This includes ignored files, like CSS partials.
+fn rerun_if_changed(dir: &Path, walk: &mut DirWalk) -> io::Result<()> {
+    if !walk.enter(dir)? {
+        walk.leave();
+        return Ok(());
+    }
+
+    println!("cargo:rerun-if-changed={}", dir.display());
+
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        if walk.skips(&entry)? {
+            continue;
+        }
+
+        let path = entry.path();
+
+        if path.is_dir() {
+            rerun_if_changed(&path, walk)?;
+        } else {
+            println!("cargo:rerun-if-changed={}", path.display());
+        }
+    }
+
+    walk.leave();
+
+    Ok(())
+}
+
+/// The mime overrides as plain strings, so they can be passed on to the
+/// `creme` macros through an env var.
+fn mime_override_strings(config: &AssetSourceConfig) -> BTreeMap<&str, &str> {
+    config
+        .mime_overrides
+        .iter()
+        .map(|(ext, mime)| (ext.as_str(), mime.as_ref()))
+        .collect()
+}
+
+#[derive(Debug, Clone)]
 pub struct CremeBundler {
-    /// The path to the public directory in the project.
+    /// The path to the public directory in the project, if there is one.
     /// This is copied to the dist directory.
-    public_dir: PathBuf,
+    public_dir: Option<PathBuf>,
+
+    /// Whether the public directory is copied to the dist directory.
+    copy_public: bool,
+
+    /// Paths in the public directory that aren't copied.
+    public_excludes: Vec<PathBuf>,
 
     /// Contains the source directory and the assets to be processed.
     assets: AssetSource,
 
-    /// Path to write the assets to, relative to the dist directory.
-    /// Typically, this would be your build's `out_dir/dist/assets` folder.
-    out_assets_dir: PathBuf,
+    /// Path to write the assets to, relative to the dist directory.
+    /// Typically, this would be your build's `out_dir/dist/assets` folder.
+    out_assets_dir: PathBuf,
+
+    /// The path to write the output to, relative to the out directory.
+    /// Typically, this would be your build's `out_dir/dist` folder.
+    out_public_dir: PathBuf,
+
+    /// The path to where all the generated files are written to.
+    out_dir: PathBuf,
+
+    /// How should the output be written to the filesystem.
+    release_mode: ReleaseMode,
+
+    /// How stylesheets are processed.
+    css: CssConfig,
+
+    /// Whether to write gzip and brotli versions of the output.
+    precompress: bool,
+
+    /// The algorithm used to hash output filenames.
+    hash_algorithm: HashAlgorithm,
+
+    /// Where the hash goes in output paths.
+    hash_strategy: HashStrategy,
+
+    /// What output paths are hashed from.
+    hash_source: HashSource,
+
+    /// Where the manifest is written.
+    manifest_path: PathBuf,
+
+    /// Where the Rust module of asset URL constants is written, if anywhere.
+    rust_module: Option<PathBuf>,
+
+    /// Whether to rewrite asset references in public HTML files.
+    rewrite_html: bool,
+
+    /// Whether to minify public HTML files.
+    minify_html: bool,
+
+    /// Whether JSON assets are checked to parse.
+    validate_json: bool,
+
+    /// Whether to minify JSON assets.
+    minify_json: bool,
+
+    /// Unix permissions to set on written output files.
+    file_mode: Option<u32>,
+
+    /// Directories, relative to the assets directory, whose files aren't processed.
+    passthrough_dirs: Vec<PathBuf>,
+
+    /// Processors tried before the built-in processing, in order.
+    processors: Vec<Arc<dyn AssetProcessor>>,
+
+    /// Edits the asset URLs before the manifest is written.
+    manifest_hook: Option<ManifestHook>,
+
+    /// The globs of each asset group, by name.
+    asset_groups: Vec<(String, GlobSet)>,
+
+    /// `Cache-Control` values by glob of served paths.
+    cache_control: CacheControlRules,
+
+    /// The assets served as downloads.
+    downloads: GlobSet,
+
+    /// The largest the output can be in total, in bytes.
+    max_total_size: Option<u64>,
+
+    /// The largest any single output file can be, in bytes.
+    max_asset_size: Option<u64>,
+
+    /// Assets smaller than this many bytes are inlined as data URIs.
+    inline_threshold: Option<usize>,
+
+    /// Whether assets nothing references fail the build.
+    deny_unreferenced: bool,
+
+    /// Whether the dist directory is updated in place.
+    incremental: bool,
+
+    /// Generates a stylesheet with Tailwind.
+    #[cfg(feature = "tailwind")]
+    tailwind: Option<TailwindConfig>,
+}
+
+/// What `CremeBundler::plan` reports would be bundled.
+#[derive(Debug, Default)]
+pub struct BundlePlan {
+    pub assets: Vec<PlannedAsset>,
+}
+
+impl BundlePlan {
+    /// Files that are bundled into more than one asset, with the assets they're
+    /// bundled into. Their contents are duplicated in each of those outputs.
+    pub fn duplicated_imports(&self) -> BTreeMap<&Path, Vec<&Path>> {
+        let mut imported_by: BTreeMap<&Path, Vec<&Path>> = BTreeMap::new();
+
+        for asset in &self.assets {
+            for import in &asset.imports {
+                imported_by
+                    .entry(import.as_path())
+                    .or_default()
+                    .push(asset.source.as_path());
+            }
+        }
+
+        imported_by.retain(|_, assets| assets.len() > 1);
+        imported_by
+    }
+}
+
+/// A summary of what `CremeBundler::bundle_with_report` bundled, like for
+/// printing after a build or enforcing a size budget in CI.
+///
+/// Only assets are counted, not files copied from the public directory.
+/// In development mode nothing is processed, so only the duration is set.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct BundleReport {
+    /// The number of assets processed, including stylesheets.
+    pub asset_count: usize,
+
+    /// The number of stylesheets processed.
+    pub css_count: usize,
+
+    /// The size of the source files, in bytes. Files bundled into a stylesheet,
+    /// like `@import`ed partials, aren't counted.
+    pub total_input_bytes: u64,
+
+    /// The size of the output files, in bytes. Assets identical to an earlier
+    /// one share its output, so it's only counted once.
+    pub total_output_bytes: u64,
+
+    /// How long bundling took.
+    pub duration: Duration,
+}
+
+impl BundleReport {
+    fn from_plan(plan: &BundlePlan, duration: Duration) -> CremeResult<Self> {
+        let mut report = BundleReport {
+            duration,
+            ..Default::default()
+        };
+
+        for asset in plan.assets.iter().filter(|asset| asset.action != PlannedAction::Copy) {
+            report.asset_count += 1;
+            if asset.mime == mime::TEXT_CSS {
+                report.css_count += 1;
+            }
+
+            report.total_input_bytes += fs::metadata(&asset.source)?.len();
+            if asset.action == PlannedAction::Process {
+                report.total_output_bytes += asset.size as u64;
+            }
+        }
+
+        Ok(report)
+    }
+
+    /// How many bytes processing saved, like by minifying and sharing outputs.
+    pub fn saved_bytes(&self) -> u64 {
+        self.total_input_bytes.saturating_sub(self.total_output_bytes)
+    }
+}
+
+impl fmt::Display for BundleReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "bundled {} assets ({} stylesheets), {} -> {} bytes, in {:.2?}",
+            self.asset_count,
+            self.css_count,
+            self.total_input_bytes,
+            self.total_output_bytes,
+            self.duration,
+        )
+    }
+}
+
+/// What happens to a file when it's bundled.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PlannedAction {
+    /// Copied as it is, like files in the public directory.
+    Copy,
+    /// Processed and written under a new name.
+    Process,
+    /// Not written, because an identical output already exists.
+    Skip,
+    /// Not written, because it's inlined as a data URI.
+    Inline,
+}
+
+impl fmt::Display for PlannedAction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            PlannedAction::Copy => "copy",
+            PlannedAction::Process => "process",
+            PlannedAction::Skip => "skip",
+            PlannedAction::Inline => "inline",
+        })
+    }
+}
+
+/// A single file in a [`BundlePlan`].
+#[derive(Debug)]
+pub struct PlannedAsset {
+    /// What happens to the file.
+    pub action: PlannedAction,
+
+    /// The path to the source file.
+    pub source: PathBuf,
+
+    /// The detected mime type.
+    pub mime: Mime,
+
+    /// The URL the asset would be written to, relative to the public directory.
+    pub dest_url: String,
+
+    /// The hex-encoded blake3 hash of the processed content.
+    pub hash: String,
+
+    /// The size of the processed content, in bytes.
+    pub size: usize,
+
+    /// Other files bundled into this asset, like stylesheets from `@import`.
+    pub imports: Vec<PathBuf>,
+}
+
+impl fmt::Display for BundlePlan {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for asset in &self.assets {
+            writeln!(
+                f,
+                "{} {} -> {} ({}, {}, {} bytes)",
+                asset.action,
+                asset.source.display(),
+                asset.dest_url,
+                asset.mime,
+                &asset.hash[..8],
+                asset.size,
+            )?;
+        }
 
-    /// The path to write the output to, relative to the out directory.
-    /// Typically, this would be your build's `out_dir/dist` folder.
-    out_public_dir: PathBuf,
+        for (import, assets) in self.duplicated_imports() {
+            let assets: Vec<_> = assets.iter().map(|asset| asset.display().to_string()).collect();
+            writeln!(
+                f,
+                "{} is bundled into {} assets: {}",
+                import.display(),
+                assets.len(),
+                assets.join(", "),
+            )?;
+        }
 
-    /// The path to where all the generated files are written to.
-    out_dir: PathBuf,
+        Ok(())
+    }
+}
 
-    /// How should the output be written to the filesystem.
-    release_mode: ReleaseMode,
+/// The contents of a processed asset, ready to be written.
+struct ProcessedAsset {
+    content: Vec<u8>,
+    /// A JSON source map for the content, if one was generated.
+    source_map: Option<String>,
+    /// Other files bundled into the content, like stylesheets from `@import`.
+    imports: Vec<PathBuf>,
 }
 
 impl CremeBundler {
-    fn filename_with_hash(filename: &OsStr, content: &[u8]) -> OsString {
+    /// Adds a hash of `content` to a file name, like `style-1a2b3c4d.css`.
+    ///
+    /// Only the content is hashed, so names don't change with flattening.
+    /// A stylesheet's content has the rewritten URLs of the assets it points at,
+    /// hashes included, so `process_assets` always processes those first.
+    /// That keeps a stylesheet's hash the same between builds.
+    fn filename_with_hash(
+        filename: &OsStr,
+        content: &[u8],
+        algorithm: HashAlgorithm,
+    ) -> OsString {
         let path = Path::new(filename);
 
-        let mut digest = [0; 4];
-        blake3::Hasher::new()
-            .update(content)
-            .finalize_xof()
-            .fill(&mut digest);
-
-        let digest = digest.encode_hex::<String>();
+        let digest = Self::short_hash(content, algorithm);
 
         let filename = path.file_stem().unwrap();
         let ext = path.extension();
@@ -458,79 +2243,455 @@ impl CremeBundler {
         }
     }
 
+    /// A short hex hash of `content`, for filenames and query strings.
+    fn short_hash(content: &[u8], algorithm: HashAlgorithm) -> String {
+        (&algorithm.digest(content)[..4]).encode_hex::<String>()
+    }
+
+    /// What an asset's output path is hashed from with `HashSource::Original`:
+    /// its source and the files bundled into it, along with the URLs of the
+    /// assets its output points at, so it still gets a new hash when they do.
+    ///
+    /// Those are found by their file name, which may match more than needed,
+    /// but only costs some cache stability. Stylesheets can inline small assets,
+    /// which leaves no name to find, so all of those count for stylesheets.
+    fn original_hash_input(
+        &self,
+        path: &Path,
+        asset_type: &AssetType,
+        content: &[u8],
+        imports: &[PathBuf],
+        manifest: &Manifest,
+    ) -> io::Result<Vec<u8>> {
+        // Generated stylesheets depend on more than their source.
+        #[cfg(feature = "tailwind")]
+        if self.tailwind.as_ref().is_some_and(|tailwind| tailwind.is_input(path)) {
+            return Ok(content.to_vec());
+        }
+
+        let mut input = fs::read(path)?;
+        for import in imports {
+            input.extend(fs::read(import)?);
+        }
+
+        let css_inline_threshold = self
+            .css
+            .inline_threshold
+            .filter(|_| *asset_type == AssetType::Css);
+        let contains = |needle: &[u8]| {
+            !needle.is_empty() && content.windows(needle.len()).any(|window| window == needle)
+        };
+
+        for entry in manifest.assets.values() {
+            let file_name = entry.path().rsplit('/').next().unwrap_or_default();
+
+            let referenced = if entry.is_inline() {
+                contains(entry.url.as_bytes())
+            } else {
+                css_inline_threshold.is_some_and(|threshold| entry.size < threshold)
+                    || contains(file_name.as_bytes())
+            };
+
+            if referenced {
+                input.extend(entry.url.as_bytes());
+            }
+        }
+
+        Ok(input)
+    }
+
+    /// Processes an asset and records it in the manifest.
+    /// The output is written to `out_dir`, unless it is `None` (a dry run).
+    #[tracing::instrument(level = "debug", skip_all, fields(asset = %asset.path.display()))]
     fn process_asset(
+        &self,
         asset: &Asset,
-        out_dir: &Path,
-        assets_dir: &PathBuf,
-        _flatten: bool,
-        hashed: bool,
-    ) -> CremeResult<()> {
+        out_dir: Option<&Path>,
+        manifest: &mut Manifest,
+    ) -> CremeResult<PlannedAsset> {
         let Asset { path, asset_type } = asset;
-
-        let content = Self::process_file(path, assets_dir, asset_type)?;
+        let hashed = matches!(self.release_mode, ReleaseMode::Release { hashed: true, .. });
+
+        let src_url = paths::to_url(self.assets.src_path(path));
+        let assets_dir = self.output_dir(path);
+
+        let ProcessedAsset {
+            mut content,
+            source_map,
+            imports,
+        } = self.process_file(path, asset_type, manifest)?;
+
+        let hash_input = match (hashed, self.hash_source) {
+            (true, HashSource::Original) => Cow::Owned(
+                self.original_hash_input(path, asset_type, &content, &imports, manifest)?,
+            ),
+            _ => Cow::Borrowed(content.as_slice()),
+        };
 
         let filename = path.file_name().unwrap();
-        let filename = if hashed {
-            Self::filename_with_hash(filename, &content)
-        } else {
-            filename.to_owned()
+        let (assets_dir, filename) = match (hashed, self.hash_strategy) {
+            (false, _) => (assets_dir, filename.to_owned()),
+            (true, HashStrategy::Filename) => (
+                assets_dir,
+                Self::filename_with_hash(filename, &hash_input, self.hash_algorithm),
+            ),
+            (true, HashStrategy::DirectoryHash) => {
+                let digest = self
+                    .hash_algorithm
+                    .digest(&hash_input)
+                    .encode_hex::<String>();
+                (assets_dir.join(digest), filename.to_owned())
+            }
+            (true, HashStrategy::Query) => (assets_dir, filename.to_owned()),
+        };
+        let query_hash = (hashed && self.hash_strategy == HashStrategy::Query)
+            .then(|| Self::short_hash(&hash_input, self.hash_algorithm));
+
+        if let Some(source_map) = source_map {
+            let mut map_filename = filename.clone();
+            map_filename.push(".map");
+
+            let map_file_path = assets_dir.join(&map_filename);
+            let map_url = paths::to_url(&map_file_path);
+            manifest.claim_output(&map_url, path)?;
+
+            if let Some(out_dir) = out_dir {
+                self.write_output(&out_dir.join(&map_file_path), source_map.as_bytes())?;
+            }
+
+            // The map lives next to the stylesheet, so a relative URL is enough.
+            content.extend_from_slice(
+                format!("\n/*# sourceMappingURL={} */", map_filename.to_string_lossy()).as_bytes(),
+            );
+
+            let map_entry = ManifestEntry::new(map_url, &mime::APPLICATION_JSON, source_map.as_bytes());
+            manifest.assets.insert(format!("{src_url}.map"), map_entry);
+        }
+
+        let mime = Mime::from(asset_type.clone());
+        let output_key = (blake3::hash(&content), mime.clone());
+        let planned = |action, entry: &ManifestEntry| PlannedAsset {
+            action,
+            source: path.clone(),
+            mime: mime.clone(),
+            dest_url: entry.url.clone(),
+            hash: entry.hash.clone(),
+            size: entry.size,
+            imports: imports.clone(),
         };
 
+        if let Some(threshold) = self.inline_threshold {
+            let inlinable = *asset_type != AssetType::Css && !asset_type.is_javascript();
+
+            if inlinable && content.len() < threshold {
+                let uri = format!("data:{mime};base64,{}", BASE64_STANDARD.encode(&content));
+                let entry = ManifestEntry::new(uri, &mime, &content);
+                let planned = planned(PlannedAction::Inline, &entry);
+                manifest.assets.insert(src_url, entry);
+                return Ok(planned);
+            }
+        }
+
+        // Identical assets point to the output that was already written.
+        if let Some(entry) = manifest.outputs.get(&output_key).cloned() {
+            tracing::debug!(url = %entry.url, "identical to an existing output");
+            let planned = planned(PlannedAction::Skip, &entry);
+            manifest.assets.insert(src_url, entry);
+            return Ok(planned);
+        }
+
         let asset_file_path = assets_dir.join(filename);
+        let mut asset_url = paths::to_url(&asset_file_path);
+        manifest.claim_output(&asset_url, path)?;
 
-        {
-            let out_file_path = out_dir.join(&asset_file_path);
-            fs::write(out_file_path, content)?;
+        if let Some(out_dir) = out_dir {
+            self.write_output(&out_dir.join(&asset_file_path), &content)?;
         }
 
-        let src_path = path.strip_prefix(assets_dir).unwrap();
+        if let Some(query_hash) = query_hash {
+            asset_url = format!("{asset_url}?v={query_hash}");
+        }
 
-        let src_url = src_path.to_str().unwrap().replace('\\', "/");
-        let dest_url = asset_file_path.to_str().unwrap().replace('\\', "/");
+        let entry = ManifestEntry::new(asset_url, &mime, &content);
+        let planned = planned(PlannedAction::Process, &entry);
 
-        MANIFEST.lock().unwrap().assets.insert(src_url, dest_url);
+        manifest.outputs.insert(output_key, entry.clone());
+        manifest.assets.insert(src_url, entry);
 
-        Ok(())
+        Ok(planned)
+    }
+
+    /// Processes every asset and records them in the manifest.
+    ///
+    /// Stylesheets go last, so the assets their `url()`s point at are already in
+    /// the manifest. A stylesheet pointing at another stylesheet is put off until
+    /// that one is processed, and if a pass makes no progress, the first missing
+    /// asset is returned as the error.
+    fn process_assets(
+        &self,
+        out_dir: Option<&Path>,
+        manifest: &mut Manifest,
+    ) -> CremeResult<Vec<PlannedAsset>> {
+        let mut planned = self
+            .assets
+            .sources
+            .iter()
+            .map(|asset| self.process_asset(asset, out_dir, manifest))
+            .collect::<CremeResult<Vec<_>>>()?;
+
+        let mut pending: Vec<&Asset> = self.assets.css_sources.iter().collect();
+
+        while !pending.is_empty() {
+            let mut deferred = Vec::new();
+            let mut first_error = None;
+
+            for asset in &pending {
+                match self.process_asset(asset, out_dir, manifest) {
+                    Ok(asset) => planned.push(asset),
+                    Err(err @ CremeError::Css(css::BundleError::MissingAsset { .. })) => {
+                        deferred.push(*asset);
+                        first_error.get_or_insert(err);
+                    }
+                    Err(err) => return Err(err),
+                }
+            }
+
+            if deferred.len() == pending.len() {
+                return Err(first_error.unwrap());
+            }
+
+            pending = deferred;
+        }
+
+        Ok(planned)
     }
 
     fn process_file(
+        &self,
         path: impl Into<PathBuf>,
-        assets_dir: &PathBuf,
         asset_type: &AssetType,
-    ) -> CremeResult<Vec<u8>> {
+        manifest: &Manifest,
+    ) -> CremeResult<ProcessedAsset> {
+        let css_config = &self.css;
         let path: PathBuf = path.into();
+
+        if self.is_passthrough(&path) {
+            return Ok(ProcessedAsset {
+                content: fs::read(&path)?,
+                source_map: None,
+                imports: Vec::new(),
+            });
+        }
+
+        if let Some(processor) = self.processors.iter().find(|p| p.handles(asset_type)) {
+            let ctx = ProcessContext {
+                src_path: self.assets.src_path(&path),
+                manifest,
+            };
+
+            return Ok(ProcessedAsset {
+                content: processor.process(&path, &ctx)?,
+                source_map: None,
+                imports: Vec::new(),
+            });
+        }
+
         Ok(match asset_type {
             AssetType::Css => {
-                // TODO: config, maybe modularize this?
-                // Also lots of copying here.
-                let parser_options = ParserOptions {
-                    flags: ParserFlags::NESTING | ParserFlags::CUSTOM_MEDIA,
-                    ..Default::default()
-                };
+                let _span = tracing::debug_span!("css").entered();
+
+                // TODO: Lots of copying here.
+                let targets = Browsers::from_browserslist(&css_config.browserslist)
+                    .map_err(|err| CremeError::Browserslist(err.to_string()))?;
 
-                let targets = lightningcss::targets::Browsers::from_browserslist([">= 0.25%"]).unwrap();
+                #[cfg(feature = "tailwind")]
+                let generated = match &self.tailwind {
+                    Some(tailwind) if tailwind.is_input(&path) => Some(tailwind.generate()?),
+                    _ => None,
+                };
+                #[cfg(not(feature = "tailwind"))]
+                let generated: Option<String> = None;
+
+                let css = css::process_css(
+                    &path,
+                    generated.as_deref(),
+                    targets,
+                    &self.assets,
+                    css_config,
+                    manifest,
+                )?;
+
+                ProcessedAsset {
+                    content: css.code.into_bytes(),
+                    source_map: css.source_map,
+                    imports: css.imports,
+                }
+            }
+            AssetType::Json(_) if self.validate_json || self.minify_json => ProcessedAsset {
+                content: json::process_json(&path, fs::read(&path)?, self.minify_json)?,
+                source_map: None,
+                imports: Vec::new(),
+            },
+            asset_type if asset_type.is_javascript() => {
+                let mut out_dir_url = paths::to_url(&self.output_dir(&path));
+
+                // With directory hashing, the script is written one level deeper,
+                // into a directory named after its own hash, which isn't known yet.
+                // No other asset is in that directory, so a placeholder name
+                // gives the same relative URLs.
+                if self.hash_strategy == HashStrategy::DirectoryHash
+                    && matches!(self.release_mode, ReleaseMode::Release { hashed: true, .. })
+                {
+                    out_dir_url.push_str("/_");
+                }
 
-                css::process_css(&path, parser_options, targets, assets_dir).into_bytes()
+                let code = fs::read_to_string(&path)?;
+                let code = js::rewrite_wasm_urls(
+                    &code,
+                    &path,
+                    &self.assets,
+                    &out_dir_url,
+                    manifest,
+                );
+
+                ProcessedAsset {
+                    content: code.into_bytes(),
+                    source_map: None,
+                    imports: Vec::new(),
+                }
             }
-            _ => fs::read(&path)?,
+            _ => ProcessedAsset {
+                content: fs::read(&path)?,
+                source_map: None,
+                imports: Vec::new(),
+            },
         })
     }
 
-    fn copy_recursively(source: impl AsRef<Path>, destination: impl AsRef<Path>) -> io::Result<()> {
-        fs::create_dir_all(&destination)?;
+    /// Whether an asset is in a passthrough directory, so it's copied as it is.
+    fn is_passthrough(&self, path: &Path) -> bool {
+        let src_path = self.assets.src_path(path);
+        self.passthrough_dirs.iter().any(|dir| src_path.starts_with(dir))
+    }
+
+    /// The directory an asset is written to, relative to the dist directory.
+    /// Without flattening, outputs keep the directory they were in.
+    fn output_dir(&self, path: &Path) -> PathBuf {
+        if let ReleaseMode::Release { flatten: true, .. } = self.release_mode {
+            return self.out_assets_dir.clone();
+        }
+
+        let src_path = self.assets.src_path(path);
+        self.out_assets_dir.join(src_path.parent().unwrap())
+    }
+
+    /// Writes an output file, creating its directory if needed. A file that
+    /// already has this content isn't rewritten, so its mtime is kept.
+    fn write_output(&self, path: &Path, content: &[u8]) -> io::Result<()> {
+        if is_unchanged(path, content) {
+            tracing::trace!(path = %path.display(), "skipping unchanged file");
+            return set_file_mode(path, self.file_mode);
+        }
+
+        tracing::trace!(path = %path.display(), size = content.len(), "writing file");
+
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        fs::write(path, content)?;
+        set_file_mode(path, self.file_mode)
+    }
+
+    /// Copies the public directory into `dist_dir`, unless it is `None` (a dry run).
+    fn copy_public_files(&self, dist_dir: Option<&Path>) -> CremeResult<Vec<PlannedAsset>> {
+        let Some(public_dir) = self.public_dir.as_ref().filter(|_| self.copy_public) else {
+            return Ok(Vec::new());
+        };
+
+        let mut files = Vec::new();
+        let mut walk = DirWalk::new(self.assets.source_config.follow_symlinks);
+        self.collect_public_files(public_dir, Path::new(""), &mut walk, &mut files)?;
+
+        let mut planned = Vec::with_capacity(files.len());
+        for (source, destination) in files {
+            let content = fs::read(&source)?;
+
+            if let Some(dist_dir) = dist_dir {
+                tracing::trace!(path = %source.display(), "copying public file");
+                self.write_output(&dist_dir.join(&destination), &content)?;
+            }
+
+            let mime = self.assets.source_config.guess_mime(&source);
+            let entry = ManifestEntry::new(paths::to_url(&destination), &mime, &content);
+
+            planned.push(PlannedAsset {
+                action: PlannedAction::Copy,
+                source,
+                mime,
+                dest_url: entry.url,
+                hash: entry.hash,
+                size: entry.size,
+                imports: Vec::new(),
+            });
+        }
+
+        Ok(planned)
+    }
+
+    /// Lists the files in a directory, with the path each one is copied to.
+    /// Symlinks are skipped, unless they're followed. Then, unlike assets,
+    /// a directory linked from several places is copied to each of them,
+    /// since every path is served. A symlink cycle is an error.
+    ///
+    /// Excluded paths are skipped without being read.
+    fn collect_public_files(
+        &self,
+        source: &Path,
+        destination: &Path,
+        walk: &mut DirWalk,
+        files: &mut Vec<(PathBuf, PathBuf)>,
+    ) -> io::Result<()> {
+        walk.enter(source)?;
+
         for entry in fs::read_dir(source)? {
             let entry = entry?;
-            let filetype = entry.file_type()?;
-            if filetype.is_dir() {
-                Self::copy_recursively(entry.path(), destination.as_ref().join(entry.file_name()))?;
+            if walk.skips(&entry)? {
+                continue;
+            }
+
+            let path = entry.path();
+            let destination = destination.join(entry.file_name());
+
+            if self.public_excludes.contains(&destination) {
+                continue;
+            }
+
+            // `fs::metadata` follows symlinks, unlike `DirEntry::file_type`.
+            if fs::metadata(&path)?.is_dir() {
+                self.collect_public_files(&path, &destination, walk, files)?;
             } else {
-                fs::copy(entry.path(), destination.as_ref().join(entry.file_name()))?;
+                files.push((path, destination));
             }
         }
+
+        walk.leave();
+
         Ok(())
     }
 
+    /// Bundles the assets. Progress is reported through `tracing`, so installing
+    /// a subscriber in `build.rs` shows what was processed and how long it took.
     pub fn bundle(&self) -> CremeResult<()> {
+        self.bundle_with_report().map(|_| ())
+    }
+
+    /// Like `bundle`, but returns a summary of what was bundled,
+    /// with counts and sizes of the assets.
+    pub fn bundle_with_report(&self) -> CremeResult<BundleReport> {
+        let _span = tracing::info_span!("bundle").entered();
+        let start = Instant::now();
+
         let CremeBundler {
             public_dir,
             assets,
@@ -538,40 +2699,316 @@ impl CremeBundler {
             out_public_dir,
             out_dir,
             release_mode,
+            precompress,
+            manifest_path,
+            rust_module,
+            rewrite_html,
+            minify_html,
+            file_mode,
+            asset_groups,
+            cache_control,
+            downloads,
+            deny_unreferenced,
+            incremental,
+            manifest_hook,
             ..
         } = self;
 
-        if let ReleaseMode::Release { flatten, hashed } = release_mode {
+        if let ReleaseMode::Release { hashed, .. } = release_mode {
             let dist_dir = out_dir.join(out_public_dir);
 
-            // Remove dist directory if it exists
-            if out_dir.exists() {
-                fs::remove_dir_all(out_dir)?;
+            // Incremental bundles remove stale outputs once the manifest is written.
+            if !*incremental {
+                self.clean()?;
             }
 
+            let mut manifest = Manifest::default();
+
             // Create assets directory
-            fs::create_dir_all(&dist_dir.join(out_assets_dir))?;
+            fs::create_dir_all(dist_dir.join(out_assets_dir))?;
 
             // Copy public assets
-            Self::copy_recursively(public_dir, &dist_dir)?;
+            let mut report = BundlePlan {
+                assets: tracing::debug_span!("copy_public", dir = ?public_dir)
+                    .in_scope(|| self.copy_public_files(Some(&dist_dir)))?,
+            };
 
             // Process assets
-            for asset in &assets.sources {
-                Self::process_asset(asset, &dist_dir, out_assets_dir, *flatten, *hashed)?;
+            report.assets.extend(self.process_assets(Some(&dist_dir), &mut manifest)?);
+            self.check_budget(&report)?;
+
+            // Shared partials end up in every stylesheet that imports them.
+            if std::env::var("OUT_DIR").is_ok() {
+                for (import, assets) in report.duplicated_imports() {
+                    println!(
+                        "cargo:warning={} is bundled into {} stylesheets, duplicating its rules",
+                        import.display(),
+                        assets.len(),
+                    );
+                }
             }
 
-            // Process CSS assets
-            for asset in &assets.css_sources {
-                Self::process_asset(asset, &dist_dir, out_assets_dir, *flatten, *hashed)?;
+            if let Some(hook) = manifest_hook {
+                manifest.apply_hook(hook);
             }
+            manifest.add_groups(asset_groups);
+            manifest.add_cache_control(cache_control);
+            manifest.add_downloads(downloads);
+            manifest.write(manifest_path)?;
+            tracing::info!(
+                assets = manifest.assets.len(),
+                manifest = %manifest_path.display(),
+                "wrote manifest"
+            );
+
+            if let Some(rust_module) = rust_module {
+                rust_module::write_rust_module(&manifest, rust_module)?;
+            }
+
+            if *rewrite_html || *minify_html {
+                let _span = tracing::debug_span!("process_html").entered();
+                let dev_url = assets.dev_url();
+                let options = html::HtmlOptions {
+                    rewrite: rewrite_html.then_some(&manifest),
+                    dev_url: &dev_url,
+                    minify: *minify_html,
+                };
+                html::process_html_files(&dist_dir, &assets.source_config, &options)?;
+            }
+
+            // Before precompressing, so stale outputs aren't compressed or embedded.
+            if *incremental {
+                let removed = self.clean_stale()?;
+                tracing::debug!(files = removed.len(), "removed stale outputs");
+            }
+
+            // Pages are checked after they're rewritten, so they point at outputs.
+            if *deny_unreferenced {
+                let _span = tracing::debug_span!("check_references").entered();
+                let unreferenced = references::unreferenced_assets(
+                    &manifest,
+                    &report,
+                    &dist_dir,
+                    Path::new(references::SOURCE_DIR),
+                )?;
+
+                if !unreferenced.is_empty() {
+                    return Err(CremeError::UnreferencedAsset(unreferenced));
+                }
+            }
+
+            let encodings: &[Encoding] = if *precompress {
+                let _span = tracing::debug_span!("precompress").entered();
+                compress::write_precompressed(&dist_dir, &assets.source_config, *file_mode)?;
+                &Encoding::ALL
+            } else {
+                &[]
+            };
+
+            let immutable_dir = dist_dir.join(out_assets_dir);
+            tracing::debug_span!("embed").in_scope(|| {
+                embed::write_embedded_assets(
+                    &dist_dir,
+                    (*hashed && self.hash_strategy != HashStrategy::Query)
+                        .then_some(immutable_dir.as_path()),
+                    &assets.source_config,
+                    cache_control,
+                    &manifest.downloads(),
+                    encodings,
+                    &out_dir.join(EMBED_FILE),
+                )
+            })?;
+
+            let report = BundleReport::from_plan(&report, start.elapsed())?;
+            tracing::info!("{report}");
+
+            Ok(report)
+        } else {
+            // Assets are served as they are, so the manifest maps each one to itself.
+            let mut manifest = Manifest::default();
+            let dev_url = assets.dev_url();
+
+            for Asset { path, asset_type } in assets.sources.iter().chain(&assets.css_sources) {
+                let src_url = paths::to_url(assets.src_path(path));
+                let url = format!("{dev_url}/{src_url}");
+                let content = fs::read(path)?;
+                let mime = Mime::from(asset_type.clone());
+
+                manifest
+                    .assets
+                    .insert(src_url, ManifestEntry::new(url, &mime, &content));
+            }
+
+            if let Some(hook) = manifest_hook {
+                manifest.apply_hook(hook);
+            }
+            manifest.add_groups(asset_groups);
+            manifest.write(manifest_path)?;
+
+            if let Some(rust_module) = rust_module {
+                rust_module::write_rust_module(&manifest, rust_module)?;
+            }
+
+            Ok(BundleReport {
+                duration: start.elapsed(),
+                ..Default::default()
+            })
+        }
+    }
+
+    /// Checks the planned output against the size budgets, if there are any.
+    fn check_budget(&self, plan: &BundlePlan) -> CremeResult<()> {
+        let written = plan.assets.iter().filter(|asset| {
+            matches!(asset.action, PlannedAction::Copy | PlannedAction::Process)
+        });
+
+        let mut total = 0;
+        for asset in written {
+            let size = asset.size as u64;
+            total += size;
+
+            if let Some(limit) = self.max_asset_size.filter(|&limit| size > limit) {
+                return Err(CremeError::BudgetExceeded {
+                    what: asset.source.display().to_string(),
+                    size,
+                    limit,
+                });
+            }
+        }
+
+        if let Some(limit) = self.max_total_size.filter(|&limit| total > limit) {
+            return Err(CremeError::BudgetExceeded {
+                what: "the bundle".to_string(),
+                size: total,
+                limit,
+            });
+        }
+
+        Ok(())
+    }
+
+    /// Removes everything `bundle` generates: the dist directory, the manifest,
+    /// the embedded assets module and the Rust module, if there is one.
+    /// Anything else in the out dir is left alone.
+    pub fn clean(&self) -> CremeResult<()> {
+        let dist_dir = self.out_dir.join(&self.out_public_dir);
+        if dist_dir.exists() {
+            fs::remove_dir_all(&dist_dir)?;
+        }
 
-            let file = File::create(out_dir.join(MANIFEST_FILE))?;
-            let writer = BufWriter::new(file);
-            serde_json::to_writer_pretty(writer, &*MANIFEST)?;
+        let embed_file = self.out_dir.join(EMBED_FILE);
+        let files = [&self.manifest_path, &embed_file]
+            .into_iter()
+            .chain(&self.rust_module);
+
+        for file in files {
+            if file.exists() {
+                fs::remove_file(file)?;
+            }
         }
 
         Ok(())
     }
+
+    /// Copies the public directory over the dist directory, without cleaning it
+    /// first like `bundle` does, such as after a public file is edited.
+    /// Unchanged files aren't rewritten. Files removed from the public
+    /// directory are left behind, see [`CremeBundler::clean_stale`].
+    ///
+    /// Does nothing if the public directory isn't copied, see [`Creme::copy_public`].
+    pub fn merge_public(&self) -> CremeResult<()> {
+        let dist_dir = self.out_dir.join(&self.out_public_dir);
+        self.copy_public_files(Some(&dist_dir))?;
+        Ok(())
+    }
+
+    /// Removes files in the dist directory that the last bundle didn't write,
+    /// like old hashed versions of renamed assets, returning their paths.
+    /// Files are kept if the manifest lists them, or they're copied from the
+    /// public directory, along with their precompressed versions and source maps.
+    ///
+    /// `bundle` already starts from an empty dist directory, unless it's
+    /// [incremental](Creme::incremental), where this is run after every bundle.
+    /// Without a manifest, nothing is removed.
+    pub fn clean_stale(&self) -> CremeResult<Vec<PathBuf>> {
+        let dist_dir = self.out_dir.join(&self.out_public_dir);
+        if !dist_dir.is_dir() || !self.manifest_path.is_file() {
+            return Ok(Vec::new());
+        }
+
+        let manifest = Manifest::read(&self.manifest_path)?;
+        let mut kept: HashSet<PathBuf> = manifest
+            .assets
+            .values()
+            .filter(|entry| !entry.is_inline())
+            .map(|entry| dist_dir.join(entry.path()))
+            .collect();
+
+        if let Some(public_dir) = self.public_dir.as_ref().filter(|_| self.copy_public) {
+            let mut files = Vec::new();
+            let mut walk = DirWalk::new(self.assets.source_config.follow_symlinks);
+            self.collect_public_files(public_dir, Path::new(""), &mut walk, &mut files)?;
+            kept.extend(files.into_iter().map(|(_, destination)| dist_dir.join(destination)));
+        }
+
+        let sidecars: HashSet<PathBuf> = kept
+            .iter()
+            .flat_map(|file| {
+                let mut source_map = file.clone().into_os_string();
+                source_map.push(".map");
+
+                Encoding::ALL
+                    .map(|encoding| encoding.sidecar_path(file))
+                    .into_iter()
+                    .chain([PathBuf::from(source_map)])
+            })
+            .collect();
+
+        let mut files = Vec::new();
+        embed::collect_files(&dist_dir, &mut files)?;
+
+        let mut removed = Vec::new();
+        for file in files {
+            if !kept.contains(&file) && !sidecars.contains(&file) {
+                tracing::debug!(path = %file.display(), "removing stale output");
+                fs::remove_file(&file)?;
+                removed.push(file);
+            }
+        }
+
+        remove_empty_dirs(&dist_dir)?;
+
+        Ok(removed)
+    }
+
+    /// The path of the manifest, which maps each asset to its output URL.
+    pub fn manifest_path(&self) -> &Path {
+        &self.manifest_path
+    }
+
+    /// The directory a release bundle is written to, which is also what gets
+    /// embedded in the binary. Assets are in its `out_assets_dir`, at the paths
+    /// of their manifest URLs.
+    pub fn dist_dir(&self) -> PathBuf {
+        self.out_dir.join(&self.out_public_dir)
+    }
+
+    /// Reports what `bundle` would do, without writing anything to disk.
+    /// Assets are still processed, so hashes, sizes and CSS errors are accurate.
+    ///
+    /// In development mode nothing is bundled, so the plan is empty.
+    pub fn plan(&self) -> CremeResult<BundlePlan> {
+        let mut plan = BundlePlan::default();
+
+        if let ReleaseMode::Release { .. } = self.release_mode {
+            plan.assets = self.copy_public_files(None)?;
+            let mut manifest = Manifest::default();
+
+            plan.assets.extend(self.process_assets(None, &mut manifest)?);
+        }
+
+        Ok(plan)
+    }
 }
 
 #[derive(Error, Debug)]
@@ -605,6 +3042,561 @@ pub enum CremeError {
 
     #[error("serde error: {0}")]
     Serde(#[from] serde_json::Error),
+
+    #[error("toml error: {0}")]
+    Toml(#[from] basic_toml::Error),
+
+    #[error("config error: unsupported config file format {0}")]
+    UnsupportedConfigFormat(PathBuf),
+
+    #[error("browserslist error: {0}")]
+    Browserslist(String),
+
+    #[error("mime error: invalid mime type {0}")]
+    InvalidMime(String),
+
+    #[error("config error: unknown CSS feature or parser flag {0}")]
+    InvalidCssFlag(String),
+
+    #[error(
+        "output collision: {} and {} are both written to {url}",
+        .first.display(),
+        .second.display()
+    )]
+    OutputCollision {
+        url: String,
+        first: PathBuf,
+        second: PathBuf,
+    },
+
+    #[error("json error in {}: {source}", .path.display())]
+    Json {
+        path: PathBuf,
+        source: serde_json::Error,
+    },
+
+    #[error("html error in {}: {source}", .path.display())]
+    Html {
+        path: PathBuf,
+        source: lol_html::errors::RewritingError,
+    },
+
+    #[error("rust module error: {first} and {second} would both be named {name}")]
+    ConstNameCollision {
+        name: String,
+        first: String,
+        second: String,
+    },
+
+    #[cfg(feature = "tailwind")]
+    #[error("tailwind error: {0}")]
+    Tailwind(String),
+
+    #[error("glob error: {0}")]
+    Glob(#[from] globset::Error),
+
+    #[error("cache control error: invalid header value {0:?}")]
+    InvalidCacheControl(String),
+
+    #[error("size budget exceeded: {what} is {size} bytes, over the limit of {limit}")]
+    BudgetExceeded { what: String, size: u64, limit: u64 },
+
+    #[error("unreferenced assets: {} aren't referenced anywhere", .0.join(", "))]
+    UnreferencedAsset(Vec<String>),
+
+    #[cfg(feature = "watch")]
+    #[error("watch error: {0}")]
+    Watch(#[from] notify_debouncer_mini::notify::Error),
 }
 
 pub type CremeResult<T> = std::result::Result<T, CremeError>;
+
+#[cfg(test)]
+mod tests {
+    use std::time::SystemTime;
+
+    use super::*;
+
+    /// Writes a file in `dir`, creating its directories.
+    fn write(dir: &Path, path: &str, content: &str) {
+        let path = dir.join(path);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, content).unwrap();
+    }
+
+    /// A release bundler for the `assets` and `public` directories in `dir`,
+    /// writing to `dir/out`.
+    fn bundler(dir: &Path, configure: impl FnOnce(Creme) -> Creme) -> CremeBundler {
+        fs::create_dir_all(dir.join("assets")).unwrap();
+
+        let creme = Creme::new()
+            .set_public_dir(dir.join("public"))
+            .set_assets_dir(dir.join("assets"))
+            .unwrap()
+            .set_out_public_dir("public")
+            .set_out_assets_dir("assets")
+            .out_dir(dir.join("out"))
+            .release();
+
+        configure(creme).build().unwrap()
+    }
+
+    /// The source paths in the manifest written to `dir/out`.
+    fn manifest_keys(dir: &Path) -> Vec<String> {
+        let manifest = fs::read(dir.join("out").join(MANIFEST_FILE)).unwrap();
+        let manifest: serde_json::Value = serde_json::from_slice(&manifest).unwrap();
+        manifest["assets"]
+            .as_object()
+            .unwrap()
+            .keys()
+            .cloned()
+            .collect()
+    }
+
+    /// The files in the dist directory, relative to it, with their mtimes.
+    fn dist_files(bundler: &CremeBundler) -> BTreeMap<PathBuf, SystemTime> {
+        let dist_dir = bundler.dist_dir();
+        let mut files = Vec::new();
+        embed::collect_files(&dist_dir, &mut files).unwrap();
+
+        files
+            .into_iter()
+            .map(|file| {
+                let modified = fs::metadata(&file).unwrap().modified().unwrap();
+                (file.strip_prefix(&dist_dir).unwrap().to_path_buf(), modified)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn dev_urls_use_the_assets_dir_name() {
+        let dir = tempfile::tempdir().unwrap();
+        write(dir.path(), "static/img/cat.svg", "<svg></svg>");
+
+        let bundler = Creme::new()
+            .set_assets_dir(dir.path().join("static"))
+            .unwrap()
+            .no_public_dir()
+            .set_out_public_dir("public")
+            .set_out_assets_dir("assets")
+            .out_dir(dir.path().join("out"))
+            .development()
+            .build()
+            .unwrap();
+        bundler.bundle().unwrap();
+
+        let manifest = Manifest::read(bundler.manifest_path()).unwrap();
+        assert_eq!(manifest.assets["img/cat.svg"].url, "static/img/cat.svg");
+    }
+
+    #[test]
+    fn release_pages_rewrite_dev_urls() {
+        let dir = tempfile::tempdir().unwrap();
+        write(dir.path(), "static/img/cat.svg", "<svg></svg>");
+        write(
+            dir.path(),
+            "public/index.html",
+            r#"<img src="/static/img/cat.svg">"#,
+        );
+
+        let bundler = Creme::new()
+            .set_public_dir(dir.path().join("public"))
+            .set_assets_dir(dir.path().join("static"))
+            .unwrap()
+            .set_out_public_dir("public")
+            .set_out_assets_dir("assets")
+            .out_dir(dir.path().join("out"))
+            .release()
+            .rewrite_html(true)
+            .build()
+            .unwrap();
+        bundler.bundle().unwrap();
+
+        let manifest = Manifest::read(bundler.manifest_path()).unwrap();
+        let page = fs::read_to_string(bundler.dist_dir().join("index.html")).unwrap();
+        let url = &manifest.assets["img/cat.svg"].url;
+        assert!(url.starts_with("assets/"));
+        assert!(page.contains(&format!(r#"src="/{url}""#)), "{page}");
+    }
+
+    #[test]
+    fn stylesheets_inline_under_either_threshold() {
+        let dir = tempfile::tempdir().unwrap();
+        write(dir.path(), "assets/img/small.svg", "<svg/>");
+        write(
+            dir.path(),
+            "assets/img/medium.svg",
+            &format!("<svg>{}</svg>", " ".repeat(32)),
+        );
+        write(
+            dir.path(),
+            "assets/css/style.css",
+            "a { background: url(../img/small.svg) } b { background: url(../img/medium.svg) }",
+        );
+        let bundler = bundler(dir.path(), |creme| {
+            creme.inline_threshold(16).css_inline_threshold(64)
+        });
+        bundler.bundle().unwrap();
+
+        let manifest = Manifest::read(bundler.manifest_path()).unwrap();
+        assert!(manifest.assets["img/small.svg"].is_inline());
+        assert!(!manifest.assets["img/medium.svg"].is_inline());
+
+        let css_url = &manifest.assets["css/style.css"].url;
+        let css = fs::read_to_string(bundler.dist_dir().join(css_url)).unwrap();
+        assert_eq!(css.matches("data:image/svg+xml;base64,").count(), 2, "{css}");
+    }
+
+    #[cfg(feature = "sniff")]
+    #[test]
+    fn sniff_mime_without_a_known_extension() {
+        let dir = tempfile::tempdir().unwrap();
+        for name in ["logo", "logo.dat", "logo.txt", "logo.bin"] {
+            fs::write(dir.path().join(name), b"\x89PNG\r\n\x1a\n").unwrap();
+        }
+
+        let mut config = AssetSourceConfig {
+            sniff_mime: true,
+            ..Default::default()
+        };
+        config
+            .mime_overrides
+            .insert("bin".to_string(), mime::APPLICATION_OCTET_STREAM);
+
+        let guess = |name: &str| config.guess_mime(&dir.path().join(name));
+        assert_eq!(guess("logo"), mime::IMAGE_PNG);
+        assert_eq!(guess("logo.dat"), mime::IMAGE_PNG);
+        // Known extensions and overrides aren't sniffed.
+        assert_eq!(guess("logo.txt"), mime::TEXT_PLAIN);
+        assert_eq!(guess("logo.bin"), mime::APPLICATION_OCTET_STREAM);
+    }
+
+    #[test]
+    fn default_copies_public() {
+        let dir = tempfile::tempdir().unwrap();
+        write(dir.path(), "public/robots.txt", "User-agent: *");
+        fs::create_dir(dir.path().join("assets")).unwrap();
+
+        let bundler = Creme::default()
+            .set_public_dir(dir.path().join("public"))
+            .set_assets_dir(dir.path().join("assets"))
+            .unwrap()
+            .set_out_public_dir("public")
+            .set_out_assets_dir("assets")
+            .out_dir(dir.path().join("out"))
+            .release()
+            .build()
+            .unwrap();
+        bundler.bundle().unwrap();
+
+        assert!(bundler.dist_dir().join("robots.txt").is_file());
+    }
+
+    #[test]
+    fn css_imports_resolve_against_import_paths() {
+        let dir = tempfile::tempdir().unwrap();
+        write(dir.path(), "node_modules/normalize.css", "html { top: 0 }");
+        write(dir.path(), "vendor/reset.css", "body { margin: 0 }");
+        write(
+            dir.path(),
+            "assets/css/style.css",
+            "@import \"normalize.css\";\n@import \"reset.css\";\na { color: red }",
+        );
+        let bundler = bundler(dir.path(), |creme| {
+            let import_paths = [dir.path().join("node_modules"), dir.path().join("vendor")];
+            creme.css_import_paths(import_paths)
+        });
+
+        let processed = bundler
+            .process_file(
+                dir.path().join("assets/css/style.css"),
+                &AssetType::Css,
+                &Manifest::default(),
+            )
+            .unwrap();
+
+        let css = String::from_utf8(processed.content).unwrap();
+        assert!(css.contains("top:0"), "{css}");
+        assert!(css.contains("margin:0"), "{css}");
+        assert!(!css.contains("@import"), "{css}");
+    }
+
+    #[test]
+    fn bundlers_keep_their_own_manifests() {
+        let first_dir = tempfile::tempdir().unwrap();
+        write(first_dir.path(), "assets/first.txt", "first");
+        let second_dir = tempfile::tempdir().unwrap();
+        write(second_dir.path(), "assets/second.txt", "second");
+
+        let first = bundler(first_dir.path(), |creme| creme);
+        let second = bundler(second_dir.path(), |creme| creme);
+        first.bundle().unwrap();
+        second.bundle().unwrap();
+        // Bundling again starts from an empty manifest too.
+        first.bundle().unwrap();
+
+        assert_eq!(manifest_keys(first_dir.path()), ["first.txt"]);
+        assert_eq!(manifest_keys(second_dir.path()), ["second.txt"]);
+    }
+
+    #[test]
+    fn mime_overrides_are_embedded() {
+        let dir = tempfile::tempdir().unwrap();
+        write(dir.path(), "assets/site.webmanifest", "{}");
+        let bundler = bundler(dir.path(), |creme| {
+            creme.mime_override(".WebManifest", "application/manifest+json".parse().unwrap())
+        });
+        bundler.bundle().unwrap();
+
+        let embedded = fs::read_to_string(dir.path().join("out").join(EMBED_FILE)).unwrap();
+        assert!(
+            embedded.contains(r#"mime: "application/manifest+json""#),
+            "{embedded}"
+        );
+    }
+
+    #[test]
+    fn clean_stale_removes_unreferenced_outputs() {
+        let dir = tempfile::tempdir().unwrap();
+        write(dir.path(), "public/robots.txt", "User-agent: *");
+        write(dir.path(), "assets/js/app.js", "console.log(1);");
+        let bundler = bundler(dir.path(), |creme| creme);
+        bundler.bundle().unwrap();
+
+        let dist_dir = dir.path().join("out/public");
+        write(&dist_dir, "assets/stale-0123abcd.js", "console.log(0);");
+        write(&dist_dir, "old/page.html", "<h1>Old</h1>");
+
+        let mut removed = bundler.clean_stale().unwrap();
+        removed.sort();
+        assert_eq!(
+            removed,
+            [
+                dist_dir.join("assets/stale-0123abcd.js"),
+                dist_dir.join("old/page.html"),
+            ]
+        );
+
+        let manifest = Manifest::read(bundler.manifest_path()).unwrap();
+        assert!(dist_dir.join(&manifest.assets["js/app.js"].url).is_file());
+        assert!(dist_dir.join("robots.txt").is_file());
+        assert!(!dist_dir.join("old").exists());
+    }
+
+    #[test]
+    fn bundle_report_counts_a_fixture() {
+        let dir = tempfile::tempdir().unwrap();
+        write(dir.path(), "public/robots.txt", "User-agent: *");
+        write(dir.path(), "assets/css/style.css", "a  {  color:  red  }");
+        write(dir.path(), "assets/img/cat.svg", "<svg></svg>");
+        write(dir.path(), "assets/img/copy.svg", "<svg></svg>");
+        let bundler = bundler(dir.path(), |creme| creme);
+
+        let report = bundler.bundle_with_report().unwrap();
+        assert_eq!(report.asset_count, 3);
+        assert_eq!(report.css_count, 1);
+        assert_eq!(report.total_input_bytes, 20 + 11 + 11);
+
+        // Minified, and the copy shares the first image's output.
+        let manifest = Manifest::read(bundler.manifest_path()).unwrap();
+        let css_size = manifest.assets["css/style.css"].size as u64;
+        assert!(css_size < 20);
+        assert_eq!(report.total_output_bytes, css_size + 11);
+        assert_eq!(report.saved_bytes(), 20 + 11 - css_size);
+    }
+
+    #[derive(Debug)]
+    struct Banner;
+
+    impl AssetProcessor for Banner {
+        fn handles(&self, asset_type: &AssetType) -> bool {
+            asset_type.is_javascript()
+        }
+
+        fn process(&self, path: &Path, _ctx: &ProcessContext) -> CremeResult<Vec<u8>> {
+            let mut content = b"/* (c) Example Co. */\n".to_vec();
+            content.extend(fs::read(path)?);
+            Ok(content)
+        }
+    }
+
+    #[test]
+    fn processor_output_is_bundled() {
+        let dir = tempfile::tempdir().unwrap();
+        write(dir.path(), "assets/js/app.js", "console.log(1);");
+        let bundler = bundler(dir.path(), |creme| creme.with_processor(Box::new(Banner)));
+        bundler.bundle().unwrap();
+
+        let expected = b"/* (c) Example Co. */\nconsole.log(1);";
+        let manifest = Manifest::read(bundler.manifest_path()).unwrap();
+        let entry = &manifest.assets["js/app.js"];
+
+        let filename = CremeBundler::filename_with_hash(
+            OsStr::new("app.js"),
+            expected,
+            HashAlgorithm::default(),
+        );
+        assert_eq!(entry.url, format!("assets/{}", filename.to_string_lossy()));
+        assert_eq!(entry.size, expected.len());
+        let written = fs::read(dir.path().join("out/public").join(&entry.url)).unwrap();
+        assert_eq!(written, expected);
+    }
+
+    #[test]
+    fn identical_assets_share_an_output() {
+        let dir = tempfile::tempdir().unwrap();
+        write(dir.path(), "assets/img/cat.svg", "<svg></svg>");
+        write(dir.path(), "assets/img/copy/cat.svg", "<svg></svg>");
+        write(dir.path(), "assets/cat.txt", "<svg></svg>");
+        let bundler = bundler(dir.path(), |creme| creme);
+        bundler.bundle().unwrap();
+
+        let manifest = Manifest::read(bundler.manifest_path()).unwrap();
+        let svg = &manifest.assets["img/cat.svg"];
+        assert_eq!(manifest.assets["img/copy/cat.svg"].url, svg.url);
+
+        // The same bytes with another mime type get their own output.
+        let txt = &manifest.assets["cat.txt"];
+        assert_ne!(txt.url, svg.url);
+        assert_eq!(txt.mime, "text/plain");
+
+        let outputs: Vec<_> = dist_files(&bundler)
+            .into_keys()
+            .filter(|file| file.starts_with("assets"))
+            .collect();
+        assert_eq!(outputs.len(), 2, "{outputs:?}");
+    }
+
+    #[test]
+    fn incremental_bundle_skips_unchanged_outputs() {
+        let dir = tempfile::tempdir().unwrap();
+        write(dir.path(), "public/robots.txt", "User-agent: *");
+        write(dir.path(), "public/docs/index.html", "<h1>Docs</h1>");
+        write(dir.path(), "assets/css/style.css", "body { color: red }");
+        let bundler = bundler(dir.path(), |creme| {
+            creme.incremental(true).precompress(true)
+        });
+
+        bundler.bundle().unwrap();
+
+        // Back-date every output, so any rewrite shows up as a newer mtime.
+        let old = SystemTime::UNIX_EPOCH + Duration::from_secs(1_000_000);
+        for file in dist_files(&bundler).keys() {
+            File::options()
+                .write(true)
+                .open(bundler.dist_dir().join(file))
+                .unwrap()
+                .set_modified(old)
+                .unwrap();
+        }
+
+        bundler.bundle().unwrap();
+
+        let files = dist_files(&bundler);
+        assert!(files.contains_key(Path::new("robots.txt")));
+        assert!(files.contains_key(Path::new("docs/index.html")));
+        for (file, modified) in files {
+            assert_eq!(modified, old, "{} was rewritten", file.display());
+        }
+    }
+
+    #[test]
+    fn incremental_bundle_removes_stale_outputs() {
+        let dir = tempfile::tempdir().unwrap();
+        write(dir.path(), "public/robots.txt", "User-agent: *");
+        write(dir.path(), "assets/js/app.js", "console.log(1);");
+        let bundler = bundler(dir.path(), |creme| creme.incremental(true));
+
+        bundler.bundle().unwrap();
+        let old_url = Manifest::read(bundler.manifest_path()).unwrap().assets["js/app.js"]
+            .url
+            .clone();
+
+        let dist_dir = bundler.dist_dir();
+        write(dir.path(), "assets/js/app.js", "console.log(2);");
+        write(&dist_dir, "assets/stale-0123abcd.js", "console.log(0);");
+        write(&dist_dir, "old/page.html", "<h1>Old</h1>");
+        bundler.bundle().unwrap();
+
+        let manifest = Manifest::read(bundler.manifest_path()).unwrap();
+        let new_url = &manifest.assets["js/app.js"].url;
+        assert_ne!(*new_url, old_url);
+
+        let files = dist_files(&bundler);
+        assert!(files.contains_key(Path::new(new_url)));
+        assert!(files.contains_key(Path::new("robots.txt")));
+        assert!(!files.contains_key(Path::new(&old_url)));
+        assert!(!files.contains_key(Path::new("assets/stale-0123abcd.js")));
+        assert!(!dist_dir.join("old").exists());
+    }
+
+    #[test]
+    fn incremental_bundle_removes_stale_sidecars() {
+        let dir = tempfile::tempdir().unwrap();
+        write(dir.path(), "public/robots.txt", &"User-agent: *\n".repeat(100));
+        let bundler = bundler(dir.path(), |creme| {
+            creme.incremental(true).precompress(true)
+        });
+
+        bundler.bundle().unwrap();
+        let robots = bundler.dist_dir().join("robots.txt");
+        for encoding in Encoding::ALL {
+            assert!(encoding.sidecar_path(&robots).exists());
+        }
+
+        // Too small to compress, so the old sidecars don't belong to it anymore.
+        write(dir.path(), "public/robots.txt", "a");
+        bundler.bundle().unwrap();
+        for encoding in Encoding::ALL {
+            assert!(!encoding.sidecar_path(&robots).exists());
+        }
+    }
+
+    #[test]
+    fn config_file_matches_the_builder() {
+        let dir = tempfile::tempdir().unwrap();
+        write(dir.path(), "public/robots.txt", "User-agent: *");
+        write(
+            dir.path(),
+            "assets/css/style.css",
+            ".a { .b { color: red } }",
+        );
+
+        let config = format!(
+            "public_dir = '{dir}/public'\n\
+             assets_dir = '{dir}/assets'\n\
+             out_dir = '{dir}/config-out'\n\
+             release_mode = 'release'\n\
+             css_include_features = ['Nesting']\n\
+             css_exclude_features = ['Colors', 'VendorPrefixes']\n\
+             css_parser_flags = ['NESTING']\n",
+            dir = dir.path().display()
+        );
+        write(dir.path(), "creme.toml", &config);
+
+        let from_config = Creme::from_config_file(dir.path().join("creme.toml"))
+            .unwrap()
+            .build()
+            .unwrap();
+        let from_builder = bundler(dir.path(), |creme| {
+            creme
+                .css_include_features(Features::Nesting)
+                .css_exclude_features(Features::Colors | Features::VendorPrefixes)
+                .css_parser_flags(ParserFlags::NESTING)
+        });
+
+        from_config.bundle().unwrap();
+        from_builder.bundle().unwrap();
+
+        let manifest =
+            |bundler: &CremeBundler| fs::read_to_string(bundler.manifest_path()).unwrap();
+        assert_eq!(manifest(&from_config), manifest(&from_builder));
+
+        let style =
+            &Manifest::read(from_builder.manifest_path()).unwrap().assets["css/style.css"].url;
+        let output =
+            |bundler: &CremeBundler| fs::read_to_string(bundler.dist_dir().join(style)).unwrap();
+        assert_eq!(output(&from_config), output(&from_builder));
+        assert!(output(&from_builder).contains(".a .b"));
+    }
+}
@@ -0,0 +1,143 @@
+use std::{
+    collections::HashSet,
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+/// Converts a relative path into a URL path, with forward slashes
+/// on every platform. Manifest keys and URLs are all built with this,
+/// so lookups agree no matter which separators a path was built with.
+pub(crate) fn to_url(path: &Path) -> String {
+    path.to_string_lossy().replace('\\', "/")
+}
+
+/// Makes `to` relative to the directory `from`, where both are URL paths
+/// from the same root, like `assets/js` and `assets/app.wasm`.
+pub(crate) fn relative_url(from: &str, to: &str) -> String {
+    let from: Vec<_> = from.split('/').filter(|segment| !segment.is_empty()).collect();
+    let to: Vec<_> = to.split('/').filter(|segment| !segment.is_empty()).collect();
+
+    let common = from
+        .iter()
+        .zip(&to)
+        .take_while(|(from, to)| from == to)
+        .count();
+
+    let mut segments = vec![".."; from.len() - common];
+    segments.extend(&to[common..]);
+    segments.join("/")
+}
+
+/// Like `std::fs::canonicalize`, but without the `\\?\` prefix Windows adds,
+/// so the result can be compared against paths that weren't canonicalized.
+pub(crate) fn canonicalize(path: impl AsRef<Path>) -> io::Result<PathBuf> {
+    let path = std::fs::canonicalize(path)?;
+
+    if cfg!(windows) {
+        if let Some(stripped) = strip_verbatim_prefix(&path) {
+            return Ok(stripped);
+        }
+    }
+
+    Ok(path)
+}
+
+/// Strips `\\?\` from `\\?\C:\...` paths. Verbatim UNC paths (`\\?\UNC\...`)
+/// are left alone, since they have no plain equivalent with a drive letter.
+fn strip_verbatim_prefix(path: &Path) -> Option<PathBuf> {
+    let rest = path.to_str()?.strip_prefix(r"\\?\")?;
+
+    if rest.starts_with(r"UNC\") {
+        return None;
+    }
+
+    Some(PathBuf::from(rest))
+}
+
+/// Keeps track of a recursive directory walk.
+///
+/// Symlinks are skipped, unless `follow_symlinks` is set. When they're followed,
+/// a directory that is one of its own ancestors (a symlink pointing back up
+/// the tree) is an error, instead of recursing forever.
+#[derive(Debug)]
+pub(crate) struct DirWalk {
+    follow_symlinks: bool,
+
+    /// The directories currently being walked, canonicalized.
+    ancestors: Vec<PathBuf>,
+
+    /// Every directory walked so far, canonicalized.
+    visited: HashSet<PathBuf>,
+}
+
+impl DirWalk {
+    pub fn new(follow_symlinks: bool) -> Self {
+        Self {
+            follow_symlinks,
+            ancestors: Vec::new(),
+            visited: HashSet::new(),
+        }
+    }
+
+    /// Whether an entry should be left out of the walk.
+    pub fn skips(&self, entry: &fs::DirEntry) -> io::Result<bool> {
+        Ok(!self.follow_symlinks && entry.file_type()?.is_symlink())
+    }
+
+    /// Enters a directory. Returns `false` if it was already walked through
+    /// another path, like a second symlink to it. Pair with [`DirWalk::leave`].
+    ///
+    /// # Errors
+    ///
+    /// This will return an error if the directory is inside itself.
+    pub fn enter(&mut self, dir: &Path) -> io::Result<bool> {
+        let canonical = canonicalize(dir)?;
+
+        if self.ancestors.contains(&canonical) {
+            return Err(io::Error::other(format!(
+                "symlink cycle: {} leads back to {}",
+                dir.display(),
+                canonical.display()
+            )));
+        }
+
+        let first_visit = self.visited.insert(canonical.clone());
+        self.ancestors.push(canonical);
+
+        Ok(first_visit)
+    }
+
+    pub fn leave(&mut self) {
+        self.ancestors.pop();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn windows_paths_become_urls() {
+        assert_eq!(to_url(Path::new(r"css\style.css")), "css/style.css");
+        assert_eq!(to_url(Path::new(r"img/icons\cat.svg")), "img/icons/cat.svg");
+        assert_eq!(to_url(&Path::new("img").join("cat.svg")), "img/cat.svg");
+    }
+
+    #[test]
+    fn relative_urls() {
+        assert_eq!(relative_url("assets/js", "assets/app.wasm"), "../app.wasm");
+        assert_eq!(relative_url("assets", "assets/pkg/a.js"), "pkg/a.js");
+        assert_eq!(relative_url("/assets/js/", "/assets/js/a.js"), "a.js");
+    }
+
+    #[test]
+    fn verbatim_prefixes() {
+        assert_eq!(
+            strip_verbatim_prefix(Path::new(r"\\?\C:\assets")),
+            Some(PathBuf::from(r"C:\assets"))
+        );
+        let unc = Path::new(r"\\?\UNC\server\share");
+        assert_eq!(strip_verbatim_prefix(unc), None);
+        assert_eq!(strip_verbatim_prefix(Path::new(r"C:\assets")), None);
+    }
+}
@@ -0,0 +1,62 @@
+use std::{fmt, path::Path};
+
+use crate::{AssetType, CremeResult, Manifest};
+
+/// Processes assets of the types it handles, instead of the built-in processing,
+/// to integrate tools like a SCSS compiler or a JS minifier without forking.
+/// Register one with `Creme::with_processor`.
+///
+/// Processors are tried in the order they were registered, and the first one
+/// that handles an asset processes it. Assets no processor handles get the
+/// built-in processing, like bundling stylesheets. Assets in passthrough
+/// directories are never processed.
+///
+/// ```rust,ignore
+/// #[derive(Debug)]
+/// struct Banner;
+///
+/// impl AssetProcessor for Banner {
+///     fn handles(&self, asset_type: &AssetType) -> bool {
+///         asset_type.is_javascript()
+///     }
+///
+///     fn process(&self, path: &Path, _ctx: &ProcessContext) -> CremeResult<Vec<u8>> {
+///         let mut content = b"/* (c) Example Co. */\n".to_vec();
+///         content.extend(std::fs::read(path)?);
+///         Ok(content)
+///     }
+/// }
+///
+/// Creme::new().with_processor(Box::new(Banner))
+/// ```
+pub trait AssetProcessor: fmt::Debug + Send + Sync {
+    /// Whether this processes assets of this type.
+    fn handles(&self, asset_type: &AssetType) -> bool;
+
+    /// Returns the processed content of the asset at `path`, which is written
+    /// to the output and hashed.
+    fn process(&self, path: &Path, ctx: &ProcessContext) -> CremeResult<Vec<u8>>;
+}
+
+/// What a processor can know about the build, passed to [`AssetProcessor::process`].
+pub struct ProcessContext<'a> {
+    pub(crate) src_path: &'a Path,
+    pub(crate) manifest: &'a Manifest,
+}
+
+impl ProcessContext<'_> {
+    /// The asset's path relative to the assets directory, like `css/style.scss`.
+    pub fn src_path(&self) -> &Path {
+        self.src_path
+    }
+
+    /// The URL an asset is served at, by its path relative to the assets directory,
+    /// like `assets/img/cat-1a2b3c4d.jpeg` for `img/cat.jpeg`. `None` if it
+    /// hasn't been processed yet. Stylesheets are processed after other assets.
+    pub fn asset_url(&self, src_url: &str) -> Option<&str> {
+        self.manifest
+            .assets
+            .get(src_url)
+            .map(|entry| entry.url.as_str())
+    }
+}
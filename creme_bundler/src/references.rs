@@ -0,0 +1,159 @@
+use std::{
+    collections::HashSet,
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use mime::Mime;
+
+use crate::{BundlePlan, Manifest, PlannedAction};
+
+/// The directory of the crate's Rust sources, relative to the build script.
+pub(crate) const SOURCE_DIR: &str = "src";
+
+/// The assets that nothing points at, by their path in the assets directory.
+///
+/// An asset is referenced if its path is a string literal in the Rust sources,
+/// like in `asset!("img/cat.jpeg")`, if it's in a group whose name is one, or
+/// if another text output points at it, like a `url()` in a stylesheet. Those
+/// can be relative, so only the file name of the asset's URL is looked for.
+pub(crate) fn unreferenced_assets(
+    manifest: &Manifest,
+    plan: &BundlePlan,
+    dist_dir: &Path,
+    source_dir: &Path,
+) -> io::Result<Vec<String>> {
+    let literals = if source_dir.is_dir() {
+        string_literals(source_dir)?
+    } else {
+        HashSet::new()
+    };
+
+    let grouped: HashSet<&String> = manifest
+        .groups
+        .iter()
+        .filter(|(name, _)| literals.contains(*name))
+        .flat_map(|(_, assets)| assets)
+        .collect();
+
+    let mut outputs = Vec::new();
+    for asset in &plan.assets {
+        if matches!(asset.action, PlannedAction::Copy | PlannedAction::Process)
+            && may_reference(&asset.mime)
+        {
+            let path = asset.dest_url.split('?').next().unwrap_or_default();
+            outputs.push(fs::read_to_string(dist_dir.join(path))?);
+        }
+    }
+
+    Ok(manifest
+        .assets
+        .iter()
+        .filter(|(src_url, entry)| {
+            let file_name = entry.url.rsplit('/').next().unwrap_or_default();
+
+            !literals.contains(*src_url)
+                && !grouped.contains(src_url)
+                && !outputs.iter().any(|output| output.contains(file_name))
+        })
+        .map(|(src_url, _)| src_url.clone())
+        .collect())
+}
+
+/// Whether a file of this type can point at other assets, like a stylesheet,
+/// a script, an HTML page or a web app manifest.
+fn may_reference(mime: &Mime) -> bool {
+    mime.type_() == mime::TEXT
+        || [mime::JAVASCRIPT, mime::JSON, mime::XML].contains(&mime.subtype())
+        || mime
+            .suffix()
+            .is_some_and(|suffix| suffix == mime::JSON || suffix == mime::XML)
+}
+
+/// The contents of every string literal in the Rust files in `dir` and the
+/// directories in it.
+fn string_literals(dir: &Path) -> io::Result<HashSet<String>> {
+    let mut literals = HashSet::new();
+    let mut dirs = vec![dir.to_path_buf()];
+
+    while let Some(dir) = dirs.pop() {
+        for entry in fs::read_dir(&dir)? {
+            let path: PathBuf = entry?.path();
+
+            if path.is_dir() {
+                dirs.push(path);
+            } else if path.extension().is_some_and(|ext| ext == "rs") {
+                literals.extend(parse_literals(&fs::read_to_string(&path)?));
+            }
+        }
+    }
+
+    Ok(literals)
+}
+
+/// Finds the string literals in Rust code, skipping comments, so examples in
+/// doc comments don't count. Escapes are kept as they are, since asset paths
+/// don't need any.
+fn parse_literals(code: &str) -> Vec<String> {
+    let bytes = code.as_bytes();
+    let find = |from: usize, pattern: &str| {
+        code.get(from..)
+            .and_then(|rest| rest.find(pattern))
+            .map_or(code.len(), |pos| from + pos)
+    };
+    let is_ident = |byte: u8| byte.is_ascii_alphanumeric() || byte == b'_';
+
+    let mut literals = Vec::new();
+    let mut i = 0;
+
+    while i < bytes.len() {
+        match bytes[i] {
+            b'/' if bytes.get(i + 1) == Some(&b'/') => i = find(i, "\n"),
+            b'/' if bytes.get(i + 1) == Some(&b'*') => i = find(i + 2, "*/") + 2,
+            b'\'' => {
+                // A char literal, like '"', or a lifetime, which has no closing quote.
+                let c = code[i + 1..].chars().next().unwrap_or_default();
+                if c == '\\' {
+                    i = find(i + 3, "'") + 1;
+                } else if code
+                    .get(i + 1 + c.len_utf8()..)
+                    .is_some_and(|rest| rest.starts_with('\''))
+                {
+                    i += c.len_utf8() + 2;
+                } else {
+                    i += 1;
+                }
+            }
+            b'"' => {
+                let start = i + 1;
+                let mut end = start;
+                while end < bytes.len() && bytes[end] != b'"' {
+                    end += if bytes[end] == b'\\' { 2 } else { 1 };
+                }
+                let end = end.min(bytes.len());
+
+                literals.push(code[start..end].to_string());
+                i = end + 1;
+            }
+            b'r' if i == 0
+                || !is_ident(bytes[i - 1])
+                || (bytes[i - 1] == b'b' && (i == 1 || !is_ident(bytes[i - 2]))) =>
+            {
+                // A raw string, like r#"..."#.
+                let hashes = bytes[i + 1..].iter().take_while(|&&b| b == b'#').count();
+                let start = i + 1 + hashes + 1;
+
+                if bytes.get(start - 1) == Some(&b'"') {
+                    let end = find(start, &format!("\"{}", "#".repeat(hashes)));
+                    literals.push(code[start..end].to_string());
+                    i = end + 1 + hashes;
+                } else {
+                    i += 1;
+                }
+            }
+            _ => i += 1,
+        }
+    }
+
+    literals
+}
@@ -0,0 +1,61 @@
+use std::{collections::HashMap, fmt::Write as _, fs, path::Path};
+
+use crate::{CremeError, CremeResult, Manifest};
+
+/// Writes a Rust module with a `&str` constant for each asset's URL, like
+/// `pub const CSS_STYLE_CSS: &str = "assets/css/style-1a2b3c4d.css";`,
+/// to be `include!`d instead of calling `creme::asset!()`.
+pub(crate) fn write_rust_module(manifest: &Manifest, out_file: &Path) -> CremeResult<()> {
+    let mut code = String::from("// Generated by creme_bundler. Do not edit.\n");
+    let mut names: HashMap<String, &str> = HashMap::new();
+
+    for (src_url, entry) in &manifest.assets {
+        let name = const_name(src_url);
+
+        if let Some(existing) = names.insert(name.clone(), src_url) {
+            return Err(CremeError::ConstNameCollision {
+                name,
+                first: existing.to_string(),
+                second: src_url.clone(),
+            });
+        }
+
+        // Debug formatting produces valid, escaped Rust string literals.
+        write!(
+            code,
+            "\n/// `{src_url}`\npub const {name}: &str = {url:?};\n",
+            url = entry.url,
+        )
+        .unwrap();
+    }
+
+    if let Some(parent) = out_file.parent() {
+        fs::create_dir_all(parent)?;
+    }
+
+    fs::write(out_file, code)?;
+
+    Ok(())
+}
+
+/// Turns an asset path into a constant name, like `css/style.css` into `CSS_STYLE_CSS`.
+/// Anything that isn't ASCII alphanumeric becomes an underscore, without repeats.
+fn const_name(src_url: &str) -> String {
+    let mut name = String::with_capacity(src_url.len());
+
+    for c in src_url.chars() {
+        if c.is_ascii_alphanumeric() {
+            name.push(c.to_ascii_uppercase());
+        } else if !name.ends_with('_') {
+            name.push('_');
+        }
+    }
+
+    let name = name.trim_matches('_');
+
+    // Identifiers can't start with a digit.
+    match name.chars().next() {
+        Some(c) if c.is_ascii_alphabetic() => name.to_string(),
+        _ => format!("_{name}"),
+    }
+}
@@ -0,0 +1,11 @@
+use std::path::Path;
+
+use mime::Mime;
+
+/// Guesses the mime type of a file from its first bytes, for files whose
+/// extension gives none. Only binary formats are recognized, since text can't
+/// be told apart reliably. `None` if nothing matches or the file can't be read.
+pub(crate) fn sniff_mime(path: &Path) -> Option<Mime> {
+    let kind = infer::get_from_path(path).ok()??;
+    kind.mime_type().parse().ok()
+}
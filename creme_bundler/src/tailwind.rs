@@ -0,0 +1,72 @@
+use std::{
+    path::{Component, Path, PathBuf},
+    process::Command,
+};
+
+use serde::Deserialize;
+
+use crate::{paths, CremeError, CremeResult};
+
+/// The Tailwind CLI, looked up on the `PATH`.
+const TAILWIND_BIN: &str = "tailwindcss";
+
+/// Generates a stylesheet with the Tailwind CLI in release mode.
+///
+/// The input is one of the assets. Its generated CSS is processed in its place,
+/// so it's minified, hashed and added to the manifest like any other stylesheet.
+/// In development mode the input is served as it is, so run
+/// `tailwindcss --watch` alongside the server.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TailwindConfig {
+    /// The stylesheet with Tailwind's directives, like `assets/css/app.css`.
+    pub input: PathBuf,
+
+    /// Globs of the files Tailwind scans for class names, like `templates/**/*.html`.
+    pub content_globs: Vec<String>,
+}
+
+impl TailwindConfig {
+    /// Whether `path` is the input stylesheet.
+    pub(crate) fn is_input(&self, path: &Path) -> bool {
+        match (paths::canonicalize(&self.input), paths::canonicalize(path)) {
+            (Ok(input), Ok(path)) => input == path,
+            _ => false,
+        }
+    }
+
+    /// Runs the Tailwind CLI, returning the generated CSS.
+    pub(crate) fn generate(&self) -> CremeResult<String> {
+        let output = Command::new(TAILWIND_BIN)
+            .arg("--input")
+            .arg(&self.input)
+            .arg("--content")
+            .arg(self.content_globs.join(","))
+            .output()
+            .map_err(|err| CremeError::Tailwind(format!("couldn't run {TAILWIND_BIN}: {err}")))?;
+
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            return Err(CremeError::Tailwind(stderr.trim().to_string()));
+        }
+
+        String::from_utf8(output.stdout)
+            .map_err(|_| CremeError::Tailwind("generated CSS is not UTF-8".to_string()))
+    }
+
+    /// The directories the content globs search, up to their first wildcard,
+    /// so the build script can rerun when templates change.
+    pub(crate) fn content_dirs(&self) -> impl Iterator<Item = PathBuf> + '_ {
+        self.content_globs.iter().map(|glob| {
+            Path::new(glob)
+                .components()
+                .take_while(|component| match component {
+                    Component::Normal(name) => !name
+                        .to_string_lossy()
+                        .contains(['*', '?', '[', '{']),
+                    _ => true,
+                })
+                .collect()
+        })
+    }
+}
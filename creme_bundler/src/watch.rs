@@ -0,0 +1,73 @@
+use std::{sync::mpsc, time::Duration};
+
+use notify_debouncer_mini::{new_debouncer, notify::RecursiveMode};
+
+use crate::{AssetSource, CremeBundler, CremeResult};
+
+/// How long to wait for file events to settle before rebundling.
+const DEBOUNCE_TIMEOUT: Duration = Duration::from_millis(200);
+
+impl CremeBundler {
+    /// Bundles the assets, then watches the assets and public directories
+    /// and bundles again whenever something changes.
+    ///
+    /// This blocks the current thread forever, so it is meant to be run on
+    /// its own thread in a development binary. Errors while rebundling are
+    /// printed and don't stop the watcher.
+    ///
+    /// Only release mode writes any output, so the bundler should be built
+    /// with `Creme::release`.
+    ///
+    /// # Errors
+    ///
+    /// This will return an error if the initial bundle fails, or if the
+    /// directories can't be watched.
+    pub fn watch(&mut self) -> CremeResult<()> {
+        self.bundle()?;
+
+        let (tx, rx) = mpsc::channel();
+        let mut debouncer = new_debouncer(DEBOUNCE_TIMEOUT, tx)?;
+
+        debouncer
+            .watcher()
+            .watch(&self.assets.src_dir, RecursiveMode::Recursive)?;
+        if let Some(overlay_dir) = &self.assets.overlay_dir {
+            debouncer
+                .watcher()
+                .watch(overlay_dir, RecursiveMode::Recursive)?;
+        }
+        if let Some(public_dir) = &self.public_dir {
+            debouncer
+                .watcher()
+                .watch(public_dir, RecursiveMode::Recursive)?;
+        }
+
+        for events in rx {
+            if let Err(err) = events {
+                tracing::error!(%err, "watch error");
+                continue;
+            }
+
+            if let Err(err) = self.rebundle() {
+                tracing::error!(%err, "failed to rebundle");
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Rescans the assets directory, so added and removed files are picked up,
+    /// and bundles again.
+    fn rebundle(&mut self) -> CremeResult<()> {
+        let mut assets = AssetSource::with_config(
+            self.assets.src_dir.clone(),
+            self.assets.source_config.clone(),
+        )?;
+        if let Some(overlay_dir) = &self.assets.overlay_dir {
+            assets = assets.with_overlay(overlay_dir)?;
+        }
+
+        self.assets = assets;
+        self.bundle()
+    }
+}
@@ -1,60 +1,417 @@
-use std::{collections::HashMap, env, fs::File, path::PathBuf};
+use std::{collections::HashMap, env, fs, path::PathBuf};
 
+use base64::{prelude::BASE64_STANDARD, Engine};
 use once_cell::sync::Lazy;
 use proc_macro::TokenStream;
-use proc_macro2::Span;
 use quote::quote;
 use serde::Deserialize;
 use syn::{
     parse::{Parse, ParseStream},
-    LitStr,
+    LitStr, Token,
 };
 
+/// The manifest version this crate understands. Must match `creme_bundler`.
+const MANIFEST_VERSION: u32 = 2;
+
 #[derive(Deserialize)]
 struct Manifest {
-    assets: HashMap<String, String>,
+    assets: HashMap<String, ManifestEntry>,
+    /// Manifests without asset groups leave this out.
+    #[serde(default)]
+    groups: HashMap<String, Vec<String>>,
+}
+
+#[derive(Deserialize)]
+#[allow(dead_code)]
+struct ManifestEntry {
+    url: String,
+    mime: String,
+    size: usize,
+    hash: String,
 }
 
-static MANIFEST: Lazy<Manifest> = Lazy::new(|| {
+/// Read on its own first, so an outdated manifest gets a clear error
+/// instead of a confusing parse failure.
+#[derive(Deserialize)]
+struct ManifestVersion {
+    /// Manifests from before the version field was added are version 1.
+    #[serde(default = "legacy_version")]
+    version: u32,
+}
+
+fn legacy_version() -> u32 {
+    1
+}
+
+/// The manifest, or the reason it couldn't be read. Errors are reported where
+/// the manifest is used, instead of panicking inside the macro.
+static MANIFEST: Lazy<Result<Manifest, String>> = Lazy::new(load_manifest);
+
+fn load_manifest() -> Result<Manifest, String> {
     let manifest_dir = PathBuf::from(env::var("CREME_MANIFEST").expect("CREME_MANIFEST not set"));
 
-    let file_reader = File::open(manifest_dir).expect("Failed to open manifest file");
-    let manifest: Manifest =
-        serde_json::from_reader(file_reader).expect("Failed to parse manifest file");
+    let contents = fs::read_to_string(&manifest_dir).map_err(|err| {
+        format!(
+            "Failed to open manifest file {}: {err}",
+            manifest_dir.display()
+        )
+    })?;
 
-    manifest
-});
+    let ManifestVersion { version } = serde_json::from_str(&contents)
+        .map_err(|err| format!("Failed to parse manifest file: {err}"))?;
+    if version != MANIFEST_VERSION {
+        return Err(format!(
+            "Manifest file is version {version}, but version {MANIFEST_VERSION} is required. \
+             Rebuild it with a matching version of creme_bundler."
+        ));
+    }
+
+    serde_json::from_str(&contents).map_err(|err| format!("Failed to parse manifest file: {err}"))
+}
 
 struct StaticInput {
-    pub path: String,
+    pub path: LitStr,
 }
 
 impl Parse for StaticInput {
     fn parse(input: ParseStream) -> syn::Result<Self> {
-        let path = input.parse::<LitStr>()?.value();
+        let path = input.parse::<LitStr>()?;
         Ok(Self { path })
     }
 }
 
-pub fn asset(input: TokenStream) -> syn::Result<TokenStream> {
-    let StaticInput { path } = syn::parse::<StaticInput>(input)?;
+struct ConcatInput {
+    pub prefix: LitStr,
+    pub path: LitStr,
+}
+
+impl Parse for ConcatInput {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let prefix = input.parse::<LitStr>()?;
+        input.parse::<Token![,]>()?;
+        let path = input.parse::<LitStr>()?;
+        Ok(Self { prefix, path })
+    }
+}
+
+/// An asset looked up in the manifest.
+pub(crate) struct ResolvedAsset {
+    /// The URL of the asset, relative to the public directory.
+    pub url: String,
+    pub mime: String,
+}
+
+/// Normalizes an asset path into its key in the manifest, which always uses
+/// forward slashes, so `css\style.css` and `./css/style.css` find `css/style.css`.
+fn manifest_key(path: &str) -> String {
+    let path = path.replace('\\', "/");
+    path.trim_start_matches("./").trim_start_matches('/').to_string()
+}
+
+/// Looks up an asset in the manifest. Without a manifest, see [`resolve_unbundled`].
+///
+/// Errors point at the literal, so the offending argument is highlighted.
+pub(crate) fn resolve(path: &LitStr) -> syn::Result<ResolvedAsset> {
+    let span = path.span();
+    let path = &manifest_key(&path.value());
 
     if env::var("CREME_MANIFEST").is_err() {
-    let path = "assets/".to_string() + &path;
+        if env::var_os("CREME_REQUIRE_MANIFEST").is_some_and(|value| value != "0") {
+            return Err(syn::Error::new(span, NO_MANIFEST));
+        }
 
+        return resolve_unbundled(path).map_err(|err| syn::Error::new(span, err));
+    }
+
+    let manifest = MANIFEST
+        .as_ref()
+        .map_err(|err| syn::Error::new(span, err))?;
+
+    // Every lookup fails the same way then, so point at the likely cause instead.
+    if manifest.assets.is_empty() {
+        return Err(syn::Error::new(
+            span,
+            format!(
+                "Asset \"{path}\" not found, because the manifest has no assets. \
+                The assets directory given to creme_bundler may be empty, or the wrong one"
+            ),
+        ));
+    }
+
+    let entry = manifest.assets.get(path).ok_or(syn::Error::new(
+        span,
+        format!("Asset \"{path}\" not found in manifest"),
+    ))?;
+
+    Ok(ResolvedAsset {
+        url: entry.url.clone(),
+        mime: entry.mime.clone(),
+    })
+}
+
+/// The source assets directory, which is `CREME_ASSETS_DIR` if it's set,
+/// or `assets` in the crate's root otherwise, like without `creme_bundler`.
+fn assets_dir() -> Result<PathBuf, String> {
+    match env::var_os("CREME_ASSETS_DIR") {
+        Some(assets_dir) => Ok(PathBuf::from(assets_dir)),
+        None => {
+            let crate_dir =
+                env::var_os("CARGO_MANIFEST_DIR").ok_or("CARGO_MANIFEST_DIR not set")?;
+            Ok(PathBuf::from(crate_dir).join("assets"))
+        }
+    }
+}
+
+/// Without a manifest, like in a crate that doesn't run `creme_bundler`, assets
+/// are served straight from the [assets directory](assets_dir), under its name,
+/// so the mime type is guessed from the path.
+///
+/// The file still has to exist, and not be one the bundler skips, so a typo
+/// is caught at compile time like it is with a manifest.
+fn resolve_unbundled(path: &str) -> Result<ResolvedAsset, String> {
+    let assets_dir = assets_dir()?;
+    let file = assets_dir.join(path);
+
+    if !file.is_file() {
+        return Err(format!("Asset \"{path}\" not found in {}", assets_dir.display()));
+    }
+
+    // Files starting with an underscore, like Sass partials, aren't served.
+    if file
+        .file_name()
+        .is_some_and(|name| name.to_string_lossy().starts_with('_'))
+    {
+        return Err(format!("Asset \"{path}\" starts with an underscore, so it isn't served"));
+    }
+
+    let name = assets_dir
+        .file_name()
+        .map_or("assets".into(), |name| name.to_string_lossy());
+
+    Ok(ResolvedAsset {
+        url: format!("{name}/{path}"),
+        mime: mime_guess::from_path(path)
+            .first_or_octet_stream()
+            .to_string(),
+    })
+}
+
+const NO_MANIFEST: &str = "CREME_MANIFEST not set, so the asset isn't hashed and may not be served. Usually this means that you are not using creme_bundler in your build script";
+
+/// Expands to an asset's URL. Without a manifest, release builds also get a
+/// warning, since the bundler most likely didn't run and the URL may 404.
+/// Set `CREME_REQUIRE_MANIFEST` to make it an error instead, see [`resolve`].
+fn url_tokens(url: &str) -> proc_macro2::TokenStream {
+    if env::var("CREME_MANIFEST").is_ok() {
+        return quote! { #url };
+    }
+
+    // Proc macros can't emit warnings, but using a deprecated item does, and
+    // `debug_assertions` is checked in the calling crate rather than here.
+    quote! {
+        {
+            #[cfg(not(debug_assertions))]
+            {
+                #[deprecated(note = #NO_MANIFEST)]
+                const CREME_MANIFEST_NOT_SET: () = ();
+                let _ = CREME_MANIFEST_NOT_SET;
+            }
+            #url
+        }
+    }
+}
+
+pub(crate) fn parse_path(input: TokenStream) -> syn::Result<LitStr> {
+    let StaticInput { path } = syn::parse::<StaticInput>(input)?;
+    Ok(path)
+}
+
+pub fn asset(input: TokenStream) -> syn::Result<TokenStream> {
+    let path = parse_path(input)?;
+    let asset_path = resolve(&path)?.url;
+
+    Ok(url_tokens(&asset_path).into())
+}
+
+/// Finds the file an asset is served from. In release mode that's the
+/// processed output, and in development mode it's the source file.
+fn resolve_file(path: &LitStr) -> syn::Result<PathBuf> {
+    let url = resolve(path)?.url;
+    let key = manifest_key(&path.value());
+    let env_error = |name: &str| syn::Error::new(path.span(), format!("{name} not set"));
+
+    if env::var("CREME_RELEASE_MODE").as_deref() == Ok("release") {
+        let public_dir = env::var("CREME_PUBLIC_DIR").map_err(|_| env_error("CREME_PUBLIC_DIR"))?;
+        // Query strings only bust caches, they aren't part of the file's path.
+        let path = url.split_once('?').map_or(url.as_str(), |(path, _)| path);
+        return Ok(PathBuf::from(public_dir).join(path));
+    }
+
+    let assets_dir = assets_dir().map_err(|err| syn::Error::new(path.span(), err))?;
+
+    // Files in the environment's overlay take the place of the ones in the assets directory.
+    let overlay_file = env::var("CREME_ASSETS_OVERLAY_DIR")
+        .map(|overlay_dir| PathBuf::from(overlay_dir).join(&key))
+        .ok()
+        .filter(|file| file.is_file());
+
+    Ok(overlay_file.unwrap_or_else(|| assets_dir.join(key)))
+}
+
+/// The content of an asset the bundler inlined as a base64 data URI,
+/// which has no output file. `None` for any other URL.
+fn inlined_content(url: &str) -> Option<Vec<u8>> {
+    let (_, data) = url.strip_prefix("data:")?.split_once(";base64,")?;
+    BASE64_STANDARD.decode(data).ok()
+}
+
+pub fn asset_bytes(input: TokenStream) -> syn::Result<TokenStream> {
+    let path = parse_path(input)?;
+
+    if let Some(content) = inlined_content(&resolve(&path)?.url) {
+        let content = proc_macro2::Literal::byte_string(&content);
         return Ok(quote! {
-            #path
+            (#content as &'static [u8])
         }
         .into());
     }
 
-    let asset_path = MANIFEST.assets.get(&path).ok_or(syn::Error::new(
-        Span::call_site(),
-        format!("Asset \"{path}\" not found in manifest"),
+    let file = resolve_file(&path)?;
+    let file = file.to_string_lossy();
+
+    Ok(quote! {
+        (::core::include_bytes!(#file) as &'static [u8])
+    }
+    .into())
+}
+
+pub fn data_uri(input: TokenStream) -> syn::Result<TokenStream> {
+    let path = parse_path(input)?;
+    let ResolvedAsset { url, mime } = resolve(&path)?;
+
+    // The bundler already inlined it.
+    if url.starts_with("data:") {
+        return Ok(quote! { #url }.into());
+    }
+
+    let file = resolve_file(&path)?;
+
+    let content = fs::read(&file).map_err(|err| {
+        syn::Error::new(path.span(), format!("Failed to read {}: {err}", file.display()))
+    })?;
+    let uri = format!("data:{mime};base64,{}", BASE64_STANDARD.encode(content));
+
+    // Including the file makes the compiler rebuild when it changes.
+    let file = file.to_string_lossy();
+
+    Ok(quote! {
+        {
+            const _: &[u8] = ::core::include_bytes!(#file);
+            #uri
+        }
+    }
+    .into())
+}
+
+pub fn manifest_group(input: TokenStream) -> syn::Result<TokenStream> {
+    let name = parse_path(input)?;
+    let span = name.span();
+    let name = &name.value();
+
+    // Groups are set up in the bundler, so there's nothing to look them up in.
+    if env::var("CREME_MANIFEST").is_err() {
+        return Err(syn::Error::new(
+            span,
+            "Asset groups need a manifest, written by creme_bundler",
+        ));
+    }
+
+    let manifest = MANIFEST
+        .as_ref()
+        .map_err(|err| syn::Error::new(span, err))?;
+
+    let group = manifest.groups.get(name).ok_or(syn::Error::new(
+        span,
+        format!("Asset group \"{name}\" not found in manifest"),
     ))?;
 
+    let urls = group
+        .iter()
+        .map(|path| {
+            manifest
+                .assets
+                .get(path)
+                .map(|entry| entry.url.as_str())
+                .ok_or(syn::Error::new(
+                    span,
+                    format!("Asset \"{path}\" not found in manifest"),
+                ))
+        })
+        .collect::<syn::Result<Vec<_>>>()?;
+
     Ok(quote! {
-        #asset_path
+        {
+            const URLS: &[&str] = &[#(#urls),*];
+            URLS
+        }
     }
     .into())
 }
+
+/// The pixel densities `srcset!` looks for, besides the asset itself at 1x.
+const DENSITIES: &[&str] = &["2x", "3x", "4x"];
+
+/// The path of an asset's variant for a pixel density, with the density after
+/// an `@` at the end of the file name, like `img/cat@2x.jpg` for `img/cat.jpg`.
+fn density_variant(path: &str, density: &str) -> String {
+    let (dir, file_name) = path.rsplit_once('/').unwrap_or(("", path));
+    let variant = match file_name.rsplit_once('.') {
+        Some((stem, ext)) if !stem.is_empty() => format!("{stem}@{density}.{ext}"),
+        _ => format!("{file_name}@{density}"),
+    };
+
+    if dir.is_empty() {
+        variant
+    } else {
+        format!("{dir}/{variant}")
+    }
+}
+
+pub fn srcset(input: TokenStream) -> syn::Result<TokenStream> {
+    let path = parse_path(input)?;
+    let key = manifest_key(&path.value());
+
+    let mut candidates = vec![format!("{} 1x", resolve(&path)?.url)];
+    for density in DENSITIES {
+        let variant = LitStr::new(&density_variant(&key, density), path.span());
+
+        // Any density can be left out, like when there's only a 2x version.
+        if let Ok(asset) = resolve(&variant) {
+            candidates.push(format!("{} {density}", asset.url));
+        }
+    }
+
+    Ok(url_tokens(&candidates.join(", ")).into())
+}
+
+pub fn asset_concat(input: TokenStream) -> syn::Result<TokenStream> {
+    let ConcatInput { prefix, path } = syn::parse::<ConcatInput>(input)?;
+    let url = prefix.value() + &resolve(&path)?.url;
+
+    Ok(url_tokens(&url).into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn manifest_keys_use_forward_slashes() {
+        assert_eq!(manifest_key("css/style.css"), "css/style.css");
+        assert_eq!(manifest_key(r"css\style.css"), "css/style.css");
+        assert_eq!(manifest_key(r"img/icons\cat.svg"), "img/icons/cat.svg");
+        assert_eq!(manifest_key("./css/style.css"), "css/style.css");
+        assert_eq!(manifest_key(r".\css\style.css"), "css/style.css");
+        assert_eq!(manifest_key("/css/style.css"), "css/style.css");
+    }
+}
@@ -1,11 +1,17 @@
 use proc_macro::TokenStream;
 
 mod asset;
+mod manifest;
+mod preload;
 mod service;
 
 /// A macro that reads from the creme-manifest.json file and returns the path to the asset.
+///
+/// Without a manifest, like when `creme_bundler` isn't in the build script, the
+/// asset's unhashed path is used, with a warning in release builds. Set the
+/// `CREME_REQUIRE_MANIFEST` env var to make that an error.
 /// # Example
-/// ```rust
+/// ```rust,ignore
 /// use creme::asset;
 ///
 /// // Transforms "my_asset.png" -> "assets/my_asset-[hash].png"
@@ -19,6 +25,173 @@ pub fn asset(input: TokenStream) -> TokenStream {
     }
 }
 
+/// Like [`asset!`], but prepends a prefix to the asset's path, such as the URL
+/// of a CDN. Both are joined into a single `&'static str` at compile time.
+/// # Example
+/// ```rust,ignore
+/// use creme::asset_concat;
+///
+/// // Transforms "css/style.css" -> "https://cdn.example.com/assets/style-[hash].css"
+/// const STYLE: &str = asset_concat!("https://cdn.example.com/", "css/style.css");
+/// ```
+#[proc_macro]
+pub fn asset_concat(input: TokenStream) -> TokenStream {
+    match asset::asset_concat(input) {
+        Ok(ts) => ts,
+        Err(e) => e.to_compile_error().into(),
+    }
+}
+
+/// Like [`asset!`], but returns the asset's contents as a `&'static [u8]`,
+/// for tiny assets that are better inlined than requested, like favicons.
+/// In release mode this is the processed output, and in development mode
+/// it's the source file. Without a manifest, it's read from the assets
+/// directory, `CREME_ASSETS_DIR` or `assets` in the crate's root.
+/// # Example
+/// ```rust,ignore
+/// use creme::asset_bytes;
+///
+/// static ICON: &[u8] = asset_bytes!("img/icon.svg");
+/// ```
+#[proc_macro]
+pub fn asset_bytes(input: TokenStream) -> TokenStream {
+    match asset::asset_bytes(input) {
+        Ok(ts) => ts,
+        Err(e) => e.to_compile_error().into(),
+    }
+}
+
+/// Like [`asset_bytes!`], but returns the asset as a base64 `data:` URL, like
+/// `data:image/svg+xml;base64,...`, with the asset's mime type. Useful for
+/// inlining small images, like CSS backgrounds.
+/// # Example
+/// ```rust,ignore
+/// use creme::data_uri;
+///
+/// const ICON: &str = data_uri!("img/icon.svg");
+/// ```
+#[proc_macro]
+pub fn data_uri(input: TokenStream) -> TokenStream {
+    match asset::data_uri(input) {
+        Ok(ts) => ts,
+        Err(e) => e.to_compile_error().into(),
+    }
+}
+
+/// Like [`asset!`], but returns a `srcset` for an image and its versions for
+/// high density screens, like `assets/cat-[hash].jpg 1x, assets/cat@2x-[hash].jpg 2x`.
+///
+/// The versions are named after the image, with the density after an `@` at
+/// the end of the file name, next to it in the assets directory, like
+/// `img/cat@2x.jpg` and `img/cat@3x.jpg` for `img/cat.jpg`. Densities up to
+/// `4x` are looked for, and any of them can be left out.
+/// # Example
+/// ```rust,ignore
+/// use creme::srcset;
+///
+/// // With `img/cat.jpg` and `img/cat@2x.jpg` in the assets directory.
+/// // "assets/cat-[hash].jpg 1x, assets/cat@2x-[hash].jpg 2x"
+/// const CAT: &str = srcset!("img/cat.jpg");
+/// ```
+#[proc_macro]
+pub fn srcset(input: TokenStream) -> TokenStream {
+    match asset::srcset(input) {
+        Ok(ts) => ts,
+        Err(e) => e.to_compile_error().into(),
+    }
+}
+
+/// Returns the URLs of every asset in a group, as a `&'static [&'static str]`,
+/// in the order of their paths. Groups are set up in the bundler with
+/// `asset_group`, from globs of asset paths.
+/// # Example
+/// ```rust,ignore
+/// use creme::manifest_group;
+///
+/// // With `.asset_group("icons", "icons/*.svg")` in `build.rs`.
+/// for url in manifest_group!("icons") {
+///     println!("{url}");
+/// }
+/// ```
+#[proc_macro]
+pub fn manifest_group(input: TokenStream) -> TokenStream {
+    match asset::manifest_group(input) {
+        Ok(ts) => ts,
+        Err(e) => e.to_compile_error().into(),
+    }
+}
+
+/// A macro that returns a `Link` header value that preloads an asset, as a
+/// `&'static str`, with `as` (and `crossorigin` for fonts) picked from the
+/// asset's mime type, like `creme::preload_links` does.
+/// # Example
+/// ```rust,ignore
+/// use creme::preload;
+///
+/// // "</assets/style-[hash].css>; rel=preload; as=style"
+/// let link = preload!("css/style.css");
+/// ```
+///
+/// The value can be sent as a `Link` header, so browsers start fetching the
+/// asset before they parse the page. With axum:
+/// ```rust,ignore
+/// use axum::http::header::LINK;
+///
+/// async fn index_handler() -> impl IntoResponse {
+///     ([(LINK, preload!("css/style.css"))], index_page())
+/// }
+/// ```
+///
+/// Several values can be joined with `", "` into one header. The same value
+/// also works for `103 Early Hints`, for servers that support sending them.
+#[proc_macro]
+pub fn preload(input: TokenStream) -> TokenStream {
+    match preload::preload(input) {
+        Ok(ts) => ts,
+        Err(e) => e.to_compile_error().into(),
+    }
+}
+
+/// A macro that returns every asset and its URL, as a
+/// `&'static HashMap<String, String>`. The map is only built once.
+///
+/// The manifest is embedded in the binary. If there is no manifest,
+/// the assets directory is listed when the map is first used.
+/// # Example
+/// ```rust,ignore
+/// use creme::manifest;
+///
+/// for (path, url) in manifest!() {
+///     println!("{path} -> {url}");
+/// }
+/// ```
+#[proc_macro]
+pub fn manifest(input: TokenStream) -> TokenStream {
+    match manifest::manifest(input) {
+        Ok(ts) => ts,
+        Err(e) => e.to_compile_error().into(),
+    }
+}
+
+/// Like [`manifest!`], but maps every asset to its whole entry, a
+/// `creme::manifest::ManifestEntry` with its URL and mime type, as a
+/// `&'static HashMap<String, ManifestEntry>`.
+/// # Example
+/// ```rust,ignore
+/// use creme::manifest_entries;
+///
+/// for (path, entry) in manifest_entries!() {
+///     println!("{path} -> {} ({})", entry.url, entry.mime);
+/// }
+/// ```
+#[proc_macro]
+pub fn manifest_entries(input: TokenStream) -> TokenStream {
+    match manifest::manifest_entries(input) {
+        Ok(ts) => ts,
+        Err(e) => e.to_compile_error().into(),
+    }
+}
+
 #[proc_macro]
 pub fn service(input: TokenStream) -> TokenStream {
     match service::service(input) {
@@ -26,3 +199,43 @@ pub fn service(input: TokenStream) -> TokenStream {
         Err(e) => e.to_compile_error().into(),
     }
 }
+
+/// Like [`service!`], but for actix-web. Returns a `creme::actix::CremeResource`
+/// that serves the embedded assets in release mode, and the files on disk in
+/// development, with the same assets prefix, mime overrides and not found page.
+///
+/// It matches every path, so register it after all of your other routes.
+/// Needs `creme`'s `actix` feature.
+///
+/// # Example
+/// ```rust,ignore
+/// App::new()
+///     .route("/", web::get().to(index))
+///     .service(creme::actix_service!())
+/// ```
+#[proc_macro]
+pub fn actix_service(input: TokenStream) -> TokenStream {
+    match service::actix_service(input) {
+        Ok(ts) => ts,
+        Err(e) => e.to_compile_error().into(),
+    }
+}
+
+/// Like [`service!`], but picks whether to serve the embedded assets or the
+/// files on disk when the program starts, from the `CREME_RELEASE_MODE` env var.
+/// A release build can then serve its assets from disk, like on staging.
+/// Returns a `creme::services::CremeService`.
+///
+/// Embedding can be turned off with `creme`'s `embed` feature, to keep the
+/// binary small when the assets are always served from disk.
+/// # Example
+/// ```rust,ignore
+/// let app = Router::new().fallback_service(creme::runtime_service!());
+/// ```
+#[proc_macro]
+pub fn runtime_service(input: TokenStream) -> TokenStream {
+    match service::runtime_service(input) {
+        Ok(ts) => ts,
+        Err(e) => e.to_compile_error().into(),
+    }
+}
@@ -0,0 +1,55 @@
+use proc_macro::TokenStream;
+use proc_macro2::{Span, TokenStream as TokenStream2};
+use quote::{format_ident, quote};
+
+pub fn manifest(input: TokenStream) -> syn::Result<TokenStream> {
+    expand(input, "manifest!()", "", quote! { ::std::string::String })
+}
+
+pub fn manifest_entries(input: TokenStream) -> syn::Result<TokenStream> {
+    expand(
+        input,
+        "manifest_entries!()",
+        "entries_",
+        quote! { ::creme::manifest::ManifestEntry },
+    )
+}
+
+/// Builds the map once, with the `creme::manifest` function starting with
+/// `prefix` that reads the embedded manifest, or lists the assets directory.
+fn expand(
+    input: TokenStream,
+    name: &str,
+    prefix: &str,
+    value: TokenStream2,
+) -> syn::Result<TokenStream> {
+    if !input.is_empty() {
+        return Err(syn::Error::new(
+            Span::call_site(),
+            format!("{name} doesn't take any arguments"),
+        ));
+    }
+
+    let init = if std::env::var("CREME_MANIFEST").is_ok() {
+        let from_json = format_ident!("{prefix}from_json");
+        quote! {
+            ::creme::manifest::#from_json(::core::include_str!(::core::env!("CREME_MANIFEST")))
+        }
+    } else {
+        let from_assets_dir = format_ident!("{prefix}from_assets_dir");
+        quote! {
+            ::creme::manifest::#from_assets_dir(::core::env!("CREME_ASSETS_DIR"))
+        }
+    };
+
+    Ok(quote! {
+        {
+            static MANIFEST: ::std::sync::OnceLock<
+                ::std::collections::HashMap<::std::string::String, #value>,
+            > = ::std::sync::OnceLock::new();
+
+            MANIFEST.get_or_init(|| #init)
+        }
+    }
+    .into())
+}
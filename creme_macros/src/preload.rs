@@ -0,0 +1,29 @@
+use proc_macro::TokenStream;
+use quote::quote;
+
+use crate::asset;
+
+pub fn preload(input: TokenStream) -> syn::Result<TokenStream> {
+    let path = asset::parse_path(input)?;
+    let asset::ResolvedAsset { url, mime } = asset::resolve(&path)?;
+
+    if url.starts_with("data:") {
+        return Err(syn::Error::new(
+            path.span(),
+            "Asset is inlined as a data URI, so there's nothing to preload",
+        ));
+    }
+
+    // `as` is picked by `creme::preload::destination`, like in `preload_links`,
+    // so the value is built once, when it's first used.
+    Ok(quote! {
+        {
+            static LINK: ::std::sync::OnceLock<::std::string::String> =
+                ::std::sync::OnceLock::new();
+
+            LINK.get_or_init(|| ::creme::preload::link_header(#url, #mime))
+                .as_str()
+        }
+    }
+    .into())
+}
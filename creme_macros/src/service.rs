@@ -1,31 +1,215 @@
+use std::{collections::HashMap, path::Path};
+
 use proc_macro::TokenStream;
-use proc_macro2::Span;
+use proc_macro2::{Span, TokenStream as TokenStream2};
 use quote::quote;
 
-pub fn service(input: TokenStream) -> syn::Result<TokenStream> {
+/// Reads the mime overrides the bundler passed on, if there are any.
+fn mime_overrides() -> syn::Result<HashMap<String, String>> {
+    let Ok(overrides) = std::env::var("CREME_MIME_OVERRIDES") else {
+        return Ok(HashMap::new());
+    };
+
+    serde_json::from_str(&overrides).map_err(|err| {
+        syn::Error::new(
+            Span::call_site(),
+            format!("Failed to parse CREME_MIME_OVERRIDES: {err}"),
+        )
+    })
+}
+
+/// Calls that set up the not found page, if the bundler was configured with one.
+fn not_found_page() -> Option<TokenStream2> {
+    std::env::var("CREME_NOT_FOUND_PAGE")
+        .ok()
+        .map(|page| quote! { .not_found_page(#page) })
+}
+
+/// Calls that set up the overlay directory, if the bundler found one for its
+/// environment, like `assets.staging`.
+fn overlay_dir() -> Option<TokenStream2> {
+    std::env::var("CREME_ASSETS_OVERLAY_DIR").ok().map(|_| {
+        quote! {
+            .overlay_dir(::std::path::PathBuf::from(::core::env!("CREME_ASSETS_OVERLAY_DIR")))
+        }
+    })
+}
+
+/// Calls that set the URL prefix assets are served under, so it matches the
+/// manifest, if it isn't `/assets`.
+fn assets_prefix() -> Option<TokenStream2> {
+    let assets_dir = std::env::var("CREME_ASSETS_DIR").ok()?;
+    let public_dir = std::env::var("CREME_PUBLIC_DIR").ok()?;
+    let release = std::env::var("CREME_RELEASE_MODE").is_ok_and(|mode| mode == "release");
+
+    let prefix = assets_url_prefix(Path::new(&assets_dir), Path::new(&public_dir), release)?;
+    (prefix != "/assets").then(|| quote! { .assets_prefix(#prefix) })
+}
+
+/// The URL prefix of the assets directory. In development, that's its name,
+/// like `/static`, and in release, where it is in the public directory.
+/// `None` if it has neither.
+fn assets_url_prefix(assets_dir: &Path, public_dir: &Path, release: bool) -> Option<String> {
+    let path = match assets_dir.strip_prefix(public_dir) {
+        Ok(path) if release => path,
+        _ => Path::new(assets_dir.file_name()?),
+    };
+
+    let url = path.to_string_lossy().replace('\\', "/");
+    (!url.is_empty()).then(|| format!("/{url}"))
+}
+
+/// Calls that set up the mime overrides, for serving from disk.
+fn mime_override_calls() -> syn::Result<Vec<TokenStream2>> {
+    Ok(mime_overrides()?
+        .into_iter()
+        .map(|(ext, mime)| {
+            quote! {
+                .mime_override(#ext, #mime.parse::<::creme::mime::Mime>().unwrap())
+            }
+        })
+        .collect())
+}
+
+fn release_mode_error() -> syn::Error {
+    syn::Error::new(
+        Span::call_site(),
+        "CREME_RELEASE_MODE not set. Usually this means that you are not using creme_bundler in your build script, or it didn't bundle."
+    )
+}
+
+pub fn service(_input: TokenStream) -> syn::Result<TokenStream> {
+    let not_found_page = not_found_page();
+    let overlay_dir = overlay_dir();
+    let assets_prefix = assets_prefix();
+
     let quoted = if let Ok(env) = std::env::var("CREME_RELEASE_MODE") {
         if env == "release" {
-            // TODO: Not implemented yet. This handles embedded assets.
             quote! {
-                ::creme::services::CremeDevService::new(
-                    ::std::path::PathBuf::from(::core::env!("CREME_ASSETS_DIR")),
-                    ::std::path::PathBuf::from(::core::env!("CREME_PUBLIC_DIR"))
+                ::creme::services::CremeReleaseService::new(
+                    ::core::include!(::core::env!("CREME_EMBED"))
                 )
+                #assets_prefix
+                #not_found_page
             }
         } else {
+            // Embedded assets already have their overrides applied by the bundler.
+            let overrides = mime_override_calls()?;
+
             quote! {
                 ::creme::services::CremeDevService::new(
                     ::std::path::PathBuf::from(::core::env!("CREME_ASSETS_DIR")),
                     ::std::path::PathBuf::from(::core::env!("CREME_PUBLIC_DIR"))
                 )
+                #assets_prefix
+                #overlay_dir
+                #(#overrides)*
+                #not_found_page
             }
         }
     } else {
-        return Err(syn::Error::new(
-            Span::call_site(),
-            "CREME_RELEASE_MODE not set. Usually this means that you are not using creme_bundler in your build script, or it didn't bundle."
-        ));
+        return Err(release_mode_error());
     };
 
     Ok(quoted.into())
 }
+
+pub fn actix_service(_input: TokenStream) -> syn::Result<TokenStream> {
+    let env = std::env::var("CREME_RELEASE_MODE").map_err(|_| release_mode_error())?;
+    let assets_prefix = assets_prefix();
+    let not_found_page = not_found_page();
+
+    let resource = if env == "release" {
+        quote! {
+            ::creme::actix::CremeResource::embedded(
+                ::core::include!(::core::env!("CREME_EMBED"))
+            )
+        }
+    } else {
+        let overrides = mime_override_calls()?;
+
+        quote! {
+            ::creme::actix::CremeResource::new(
+                ::std::path::PathBuf::from(::core::env!("CREME_ASSETS_DIR")),
+                ::std::path::PathBuf::from(::core::env!("CREME_PUBLIC_DIR"))
+            )
+            #(#overrides)*
+        }
+    };
+
+    Ok(quote! {
+        #resource
+        #assets_prefix
+        #not_found_page
+    }
+    .into())
+}
+
+pub fn runtime_service(_input: TokenStream) -> syn::Result<TokenStream> {
+    let env = std::env::var("CREME_RELEASE_MODE").map_err(|_| release_mode_error())?;
+
+    // Only a release bundle has embedded assets. Whether they're compiled in
+    // is up to `creme`'s `embed` feature.
+    let embedded = if env == "release" {
+        quote! {
+            ::creme::__embedded_assets!(::core::include!(::core::env!("CREME_EMBED")))
+        }
+    } else {
+        quote! { ::core::option::Option::None }
+    };
+
+    let overrides = mime_override_calls()?;
+    let assets_prefix = assets_prefix();
+    let overlay_dir = overlay_dir();
+    let not_found_page = not_found_page();
+
+    Ok(quote! {
+        ::creme::services::CremeService::from_env(
+            #embedded,
+            ::core::env!("CREME_ASSETS_DIR"),
+            ::core::env!("CREME_PUBLIC_DIR")
+        )
+        #assets_prefix
+        #overlay_dir
+        #(#overrides)*
+        #not_found_page
+    }
+    .into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dev_prefix_is_the_dir_name() {
+        let prefix = assets_url_prefix(Path::new("/app/static"), Path::new("/app/public"), false);
+        assert_eq!(prefix.as_deref(), Some("/static"));
+
+        // Even when it's in the public directory.
+        let prefix = assets_url_prefix(
+            Path::new("/app/public/a/static"),
+            Path::new("/app/public"),
+            false,
+        );
+        assert_eq!(prefix.as_deref(), Some("/static"));
+    }
+
+    #[test]
+    fn release_prefix_is_the_path_in_public() {
+        let prefix = assets_url_prefix(
+            Path::new("/out/public/static/v1"),
+            Path::new("/out/public"),
+            true,
+        );
+        assert_eq!(prefix.as_deref(), Some("/static/v1"));
+    }
+
+    #[test]
+    fn no_prefix_without_a_name() {
+        assert_eq!(
+            assets_url_prefix(Path::new("/"), Path::new("/public"), false),
+            None
+        );
+    }
+}
@@ -11,12 +11,12 @@ async fn main() {
         // In release mode, it will embed the files in the binary. (TODO)
         .fallback_service(creme::service!().fallback(not_found_handler.into_service()));
 
-    // Uncomment this to disable hot reloading in release mode.
-    // #[cfg(debug_assertions)]
-    let app = app.layer(
-        tower_livereload::LiveReloadLayer::new()
-            .reload_interval(std::time::Duration::from_millis(100)),
-    );
+    // Reload the browser whenever the server restarts, only in development.
+    let app = if creme::is_development!() {
+        app.layer(creme::services::LiveReloadLayer::new())
+    } else {
+        app
+    };
 
     println!("Listening on http://localhost:3000");
 